@@ -0,0 +1,381 @@
+use crate::utils::*;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+// the cycle search gives up after max(n², this) steps, so it can't stall the bot on big games
+const MIN_CYCLE_SEARCH_STEPS: usize = 10_000;
+
+pub trait Constraint: Send + Sync {
+    fn allows(&self, presenter: &UserId, recipient: &UserId) -> bool;
+}
+
+/// nobody prepares a present for themselves
+pub struct NoSelfPresent;
+
+impl Constraint for NoSelfPresent {
+    fn allows(&self, presenter: &UserId, recipient: &UserId) -> bool {
+        presenter != recipient
+    }
+}
+
+impl Constraint for Exclusion {
+    fn allows(&self, presenter: &UserId, recipient: &UserId) -> bool {
+        !self.forbids(presenter, recipient)
+    }
+}
+
+/// nobody gets the same recipient they've already had
+pub struct NoRepeats(pub Vec<(UserId, UserId)>);
+
+impl Constraint for NoRepeats {
+    fn allows(&self, presenter: &UserId, recipient: &UserId) -> bool {
+        !self.0.contains(&(*presenter, *recipient))
+    }
+}
+
+#[derive(Default)]
+pub struct Constraints {
+    constraints: Vec<Box<dyn Constraint>>,
+}
+
+impl Constraints {
+    pub fn with(mut self, constraint: impl Constraint + 'static) -> Self {
+        self.constraints.push(Box::new(constraint));
+        self
+    }
+    pub fn allows(&self, presenter: &UserId, recipient: &UserId) -> bool {
+        self.constraints
+            .iter()
+            .all(|constraint| constraint.allows(presenter, recipient))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// pairs of (presenter, recipient), every user is present exactly once on each side
+    Assigned(Vec<(UserId, UserId)>),
+    Infeasible,
+}
+
+/// Finds a random assignment of recipients to presenters that satisfies all constraints.
+///
+/// It's a bipartite matching between presenters and recipients found with augmenting paths,
+/// so it always terminates and reports `Infeasible` only if there's no valid assignment at all.
+pub fn assign(users: &[UserId], constraints: &Constraints) -> Outcome {
+    let mut rng = thread_rng();
+
+    let mut presenters: Vec<usize> = (0..users.len()).collect();
+    presenters.shuffle(&mut rng);
+
+    // candidates[presenter] lists indices of users presenter may prepare a present for
+    let candidates: Vec<Vec<usize>> = (0..users.len())
+        .map(|presenter| {
+            let mut recipients: Vec<usize> = (0..users.len())
+                .filter(|&recipient| constraints.allows(&users[presenter], &users[recipient]))
+                .collect();
+            recipients.shuffle(&mut rng);
+            recipients
+        })
+        .collect();
+
+    // presenter_of[recipient] is index of the presenter currently assigned to recipient
+    let mut presenter_of: Vec<Option<usize>> = vec![None; users.len()];
+    for presenter in presenters {
+        let mut visited = vec![false; users.len()];
+        if !augment(presenter, &candidates, &mut presenter_of, &mut visited) {
+            return Outcome::Infeasible;
+        }
+    }
+
+    let pairs = presenter_of
+        .iter()
+        .enumerate()
+        .map(|(recipient, presenter)| (presenter.unwrap(), recipient));
+
+    Outcome::Assigned(to_user_pairs(users, pairs))
+}
+
+/// Finds a random assignment where presents go around a single chain, so that
+/// nobody ends up in a closed group smaller than the whole game.
+///
+/// It's a backtracking search for a hamiltonian cycle limited to a number of steps,
+/// so for big games with lots of constraints it may report `Infeasible` even if there's a cycle.
+pub fn single_cycle(users: &[UserId], constraints: &Constraints) -> Outcome {
+    // every single cycle is also a valid assignment, so there's no need to search if there's none
+    if users.is_empty() || assign(users, constraints) == Outcome::Infeasible {
+        return Outcome::Infeasible;
+    }
+
+    let mut rng = thread_rng();
+
+    let allowed: Vec<Vec<bool>> = users
+        .iter()
+        .map(|presenter| {
+            users
+                .iter()
+                .map(|recipient| constraints.allows(presenter, recipient))
+                .collect()
+        })
+        .collect();
+
+    let start = rng.gen_range(0..users.len());
+    let mut path = vec![start];
+    let mut visited = vec![false; users.len()];
+    visited[start] = true;
+    let mut steps = (users.len() * users.len()).max(MIN_CYCLE_SEARCH_STEPS);
+
+    match extend_cycle(&allowed, &mut path, &mut visited, &mut steps, &mut rng) {
+        true => {
+            let pairs = path
+                .iter()
+                .zip(path.iter().cycle().skip(1))
+                .map(|(&presenter, &recipient)| (presenter, recipient));
+            Outcome::Assigned(to_user_pairs(users, pairs))
+        }
+        false => Outcome::Infeasible,
+    }
+}
+
+fn extend_cycle(
+    allowed: &[Vec<bool>],
+    path: &mut Vec<usize>,
+    visited: &mut [bool],
+    steps: &mut usize,
+    rng: &mut impl Rng,
+) -> bool {
+    if *steps == 0 {
+        return false;
+    }
+    *steps -= 1;
+
+    let last = *path.last().unwrap();
+    if path.len() == allowed.len() {
+        return allowed[last][path[0]];
+    }
+
+    let mut next: Vec<usize> = (0..allowed.len())
+        .filter(|&recipient| !visited[recipient] && allowed[last][recipient])
+        .collect();
+    next.shuffle(rng);
+
+    for recipient in next {
+        visited[recipient] = true;
+        path.push(recipient);
+        if extend_cycle(allowed, path, visited, steps, rng) {
+            return true;
+        }
+        path.pop();
+        visited[recipient] = false;
+    }
+    false
+}
+
+// converts pairs of indices into pairs of users ordered the same way as `users`
+fn to_user_pairs(
+    users: &[UserId],
+    pairs: impl Iterator<Item = (usize, usize)>,
+) -> Vec<(UserId, UserId)> {
+    let mut pairs: Vec<(usize, usize)> = pairs.collect();
+    pairs.sort_by_key(|&(presenter, _)| presenter);
+    pairs
+        .into_iter()
+        .map(|(presenter, recipient)| (users[presenter], users[recipient]))
+        .collect()
+}
+
+fn augment(
+    presenter: usize,
+    candidates: &[Vec<usize>],
+    presenter_of: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    for &recipient in &candidates[presenter] {
+        if visited[recipient] {
+            continue;
+        }
+        visited[recipient] = true;
+
+        let reassigned = match presenter_of[recipient] {
+            Some(other) => augment(other, candidates, presenter_of, visited),
+            None => true,
+        };
+        if reassigned {
+            presenter_of[recipient] = Some(presenter);
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // draws are random, so every check is repeated
+    const RUNS: usize = 50;
+
+    fn users(count: i64) -> Vec<UserId> {
+        (1..=count).map(UserId).collect()
+    }
+
+    fn pairs(outcome: Outcome) -> Vec<(UserId, UserId)> {
+        match outcome {
+            Outcome::Assigned(pairs) => pairs,
+            Outcome::Infeasible => panic!("expected an assignment"),
+        }
+    }
+
+    // every user presents and receives exactly once
+    fn assert_is_assignment(users: &[UserId], pairs: &[(UserId, UserId)]) {
+        assert_eq!(pairs.len(), users.len());
+        for user in users {
+            assert_eq!(
+                pairs
+                    .iter()
+                    .filter(|(presenter, _)| presenter == user)
+                    .count(),
+                1
+            );
+            assert_eq!(
+                pairs
+                    .iter()
+                    .filter(|(_, recipient)| recipient == user)
+                    .count(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn nobody_presents_to_themselves() {
+        let users = users(5);
+        for _ in 0..RUNS {
+            let pairs = pairs(assign(&users, &Constraints::default().with(NoSelfPresent)));
+            assert_is_assignment(&users, &pairs);
+            assert!(pairs
+                .iter()
+                .all(|(presenter, recipient)| presenter != recipient));
+        }
+    }
+
+    #[test]
+    fn respects_exclusions() {
+        let users = users(4);
+        let exclusions = [
+            Exclusion::BothWays(UserId(1), UserId(2)),
+            Exclusion::OneWay {
+                presenter: UserId(3),
+                recipient: UserId(4),
+            },
+        ];
+        for _ in 0..RUNS {
+            let constraints = exclusions.iter().copied().fold(
+                Constraints::default().with(NoSelfPresent),
+                Constraints::with,
+            );
+            let pairs = pairs(assign(&users, &constraints));
+            assert_is_assignment(&users, &pairs);
+            for (presenter, recipient) in &pairs {
+                assert!(exclusions
+                    .iter()
+                    .all(|exclusion| !exclusion.forbids(presenter, recipient)));
+            }
+        }
+    }
+
+    #[test]
+    fn respects_previous_pairs() {
+        let users = users(4);
+        let previous = vec![
+            (UserId(1), UserId(2)),
+            (UserId(2), UserId(3)),
+            (UserId(3), UserId(4)),
+            (UserId(4), UserId(1)),
+        ];
+        for _ in 0..RUNS {
+            let constraints = Constraints::default()
+                .with(NoSelfPresent)
+                .with(NoRepeats(previous.clone()));
+            let pairs = pairs(assign(&users, &constraints));
+            assert_is_assignment(&users, &pairs);
+            assert!(pairs.iter().all(|pair| !previous.contains(pair)));
+        }
+    }
+
+    #[test]
+    fn single_user_is_infeasible() {
+        let constraints = Constraints::default().with(NoSelfPresent);
+        assert_eq!(assign(&users(1), &constraints), Outcome::Infeasible);
+        assert_eq!(single_cycle(&users(1), &constraints), Outcome::Infeasible);
+    }
+
+    #[test]
+    fn fully_excluded_users_are_infeasible() {
+        // user 1 can't prepare a present for anybody else
+        let constraints = Constraints::default()
+            .with(NoSelfPresent)
+            .with(Exclusion::OneWay {
+                presenter: UserId(1),
+                recipient: UserId(2),
+            })
+            .with(Exclusion::OneWay {
+                presenter: UserId(1),
+                recipient: UserId(3),
+            });
+        assert_eq!(assign(&users(3), &constraints), Outcome::Infeasible);
+        assert_eq!(single_cycle(&users(3), &constraints), Outcome::Infeasible);
+    }
+
+    #[test]
+    fn single_cycle_goes_through_everybody() {
+        let users = users(6);
+        for _ in 0..RUNS {
+            let pairs = pairs(single_cycle(
+                &users,
+                &Constraints::default().with(NoSelfPresent),
+            ));
+            assert_is_assignment(&users, &pairs);
+
+            // following recipients from any user gets back to them only after visiting everybody
+            let mut current = users[0];
+            for step in 1..=users.len() {
+                current = pairs
+                    .iter()
+                    .find(|(presenter, _)| *presenter == current)
+                    .unwrap()
+                    .1;
+                assert_eq!(current == users[0], step == users.len());
+            }
+        }
+    }
+
+    // users with ids of different parity can't prepare presents for each other
+    struct SameParity;
+
+    impl Constraint for SameParity {
+        fn allows(&self, presenter: &UserId, recipient: &UserId) -> bool {
+            presenter.0 % 2 == recipient.0 % 2
+        }
+    }
+
+    #[test]
+    fn single_cycle_gives_up_on_big_games() {
+        // there are assignments within each group, but no cycle through everybody,
+        // so an exhaustive search would go through every ordering of one group
+        let users = users(200);
+        let constraints = Constraints::default().with(NoSelfPresent).with(SameParity);
+        let started = std::time::Instant::now();
+        assert_eq!(single_cycle(&users, &constraints), Outcome::Infeasible);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn single_cycle_of_two_is_a_swap() {
+        let users = users(2);
+        let pairs = pairs(single_cycle(
+            &users,
+            &Constraints::default().with(NoSelfPresent),
+        ));
+        assert_eq!(pairs, vec![(UserId(1), UserId(2)), (UserId(2), UserId(1))]);
+    }
+}
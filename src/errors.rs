@@ -17,11 +17,7 @@ impl fmt::Display for UserRegistrationError {
     }
 }
 
-impl Error for UserRegistrationError {
-    fn description(&self) -> &str {
-        format!("{}", self).as_str()
-    }
-}
+impl Error for UserRegistrationError {}
 
 #[derive(Debug)]
 pub struct UserDoesNotExistError {
@@ -34,11 +30,7 @@ impl fmt::Display for UserDoesNotExistError {
     }
 }
 
-impl Error for UserDoesNotExistError {
-    fn description(&self) -> &str {
-        format!("{}", self).as_str()
-    }
-}
+impl Error for UserDoesNotExistError {}
 
 #[derive(Debug)]
 pub struct GameDoesNotExistError {
@@ -51,11 +43,7 @@ impl fmt::Display for GameDoesNotExistError {
     }
 }
 
-impl Error for GameDoesNotExistError {
-    fn description(&self) -> &str {
-        format!("{}", self).as_str()
-    }
-}
+impl Error for GameDoesNotExistError {}
 
 #[derive(Debug)]
 pub struct UserIsNotInPendingError {
@@ -73,11 +61,7 @@ impl fmt::Display for UserIsNotInPendingError {
     }
 }
 
-impl Error for UserIsNotInPendingError {
-    fn description(&self) -> &str {
-        format!("{}", self).as_str()
-    }
-}
+impl Error for UserIsNotInPendingError {}
 
 #[derive(Debug)]
 pub struct GameIsNotInPendingError {
@@ -95,11 +79,7 @@ impl fmt::Display for GameIsNotInPendingError {
     }
 }
 
-impl Error for GameIsNotInPendingError {
-    fn description(&self) -> &str {
-        format!("{}", self).as_str()
-    }
-}
+impl Error for GameIsNotInPendingError {}
 
 #[derive(Debug)]
 pub struct UserIsAlreadyInGameError {
@@ -117,11 +97,7 @@ impl fmt::Display for UserIsAlreadyInGameError {
     }
 }
 
-impl Error for UserIsAlreadyInGameError {
-    fn description(&self) -> &str {
-        format!("{}", self).as_str()
-    }
-}
+impl Error for UserIsAlreadyInGameError {}
 
 #[derive(Debug)]
 pub struct GameIsAlreadyInUserError {
@@ -139,8 +115,245 @@ impl fmt::Display for GameIsAlreadyInUserError {
     }
 }
 
-impl Error for GameIsAlreadyInUserError {
-    fn description(&self) -> &str {
-        format!("{}", self).as_str()
+impl Error for GameIsAlreadyInUserError {}
+
+#[derive(Debug)]
+pub struct UserIsNotInGameError {
+    pub user_id: UserId,
+    pub game_id: GameId,
+}
+
+impl fmt::Display for UserIsNotInGameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "User with id: {} is not in game with id: {}",
+            self.user_id, self.game_id,
+        )
+    }
+}
+
+impl Error for UserIsNotInGameError {}
+
+#[derive(Debug)]
+pub struct NoValidAssignmentError {
+    pub game_id: GameId,
+}
+
+impl fmt::Display for NoValidAssignmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "There's no way to distribute presents in game with id: {} that satisfies its exclusion rules",
+            self.game_id,
+        )
+    }
+}
+
+impl Error for NoValidAssignmentError {}
+
+#[derive(Debug)]
+pub struct NotEnoughParticipantsError {
+    pub game_id: GameId,
+}
+
+impl fmt::Display for NotEnoughParticipantsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Game with id: {} needs at least 2 participants to be run",
+            self.game_id,
+        )
+    }
+}
+
+impl Error for NotEnoughParticipantsError {}
+
+#[derive(Debug)]
+pub struct GameWasNotDrawnError {
+    pub id: GameId,
+}
+
+impl fmt::Display for GameWasNotDrawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Game with id: {} was not drawn yet", self.id.0)
+    }
+}
+
+impl Error for GameWasNotDrawnError {}
+
+#[derive(Debug)]
+pub struct GameIsAlreadyDrawnError {
+    pub id: GameId,
+}
+
+impl fmt::Display for GameIsAlreadyDrawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Game with id: {} was already drawn", self.id.0)
+    }
+}
+
+impl Error for GameIsAlreadyDrawnError {}
+
+#[derive(Debug)]
+pub struct GameWasChangedError {
+    pub id: GameId,
+}
+
+impl fmt::Display for GameWasChangedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Game with id: {} was changed at the same time, please retry",
+            self.id.0
+        )
+    }
+}
+
+impl Error for GameWasChangedError {}
+
+#[derive(Debug)]
+pub struct MessageDoesNotExistError {
+    pub id: OutboxId,
+}
+
+impl fmt::Display for MessageDoesNotExistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Message with id: {} does not exist", self.id.0)
+    }
+}
+
+impl Error for MessageDoesNotExistError {}
+
+#[derive(Debug)]
+pub struct GameIsLockedError {
+    pub id: GameId,
+}
+
+impl fmt::Display for GameIsLockedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Registration to game with id: {} is closed", self.id.0)
+    }
+}
+
+impl Error for GameIsLockedError {}
+
+#[derive(Debug)]
+pub struct WrongPasswordError {
+    pub id: GameId,
+}
+
+impl fmt::Display for WrongPasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Wrong password for game with id: {}", self.id.0)
+    }
+}
+
+impl Error for WrongPasswordError {}
+
+#[derive(Debug)]
+pub struct GameIsInviteOnlyError {
+    pub id: GameId,
+}
+
+impl fmt::Display for GameIsInviteOnlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Game with id: {} can be joined only with an invite link",
+            self.id.0
+        )
+    }
+}
+
+impl Error for GameIsInviteOnlyError {}
+
+#[derive(Debug)]
+pub struct UserIsRejectedError {
+    pub user_id: UserId,
+    pub game_id: GameId,
+}
+
+impl fmt::Display for UserIsRejectedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "User with id: {} was rejected from game with id: {}",
+            self.user_id, self.game_id,
+        )
+    }
+}
+
+impl Error for UserIsRejectedError {}
+
+#[derive(Debug)]
+pub struct UserIsAdminError {
+    pub user_id: UserId,
+    pub game_id: GameId,
+}
+
+impl fmt::Display for UserIsAdminError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "User with id: {} is already admin of game with id: {}",
+            self.user_id, self.game_id,
+        )
+    }
+}
+
+impl Error for UserIsAdminError {}
+
+#[derive(Debug)]
+pub struct GameIsNotOfferedError {
+    pub user_id: UserId,
+    pub game_id: GameId,
+}
+
+impl fmt::Display for GameIsNotOfferedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Game with id: {} is not offered to user with id: {}",
+            self.game_id, self.user_id,
+        )
+    }
+}
+
+impl Error for GameIsNotOfferedError {}
+
+#[derive(Debug)]
+pub struct UserIsNotInvitedError {
+    pub user_id: UserId,
+    pub game_id: GameId,
+}
+
+impl fmt::Display for UserIsNotInvitedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "User with id: {} is not invited to game with id: {}",
+            self.user_id, self.game_id,
+        )
+    }
+}
+
+impl Error for UserIsNotInvitedError {}
+
+#[derive(Debug)]
+pub struct UserIsNotAllowedError {
+    pub user_id: UserId,
+    pub game_id: GameId,
+}
+
+impl fmt::Display for UserIsNotAllowedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "User with id: {} is not allowed to do this in game with id: {}",
+            self.user_id, self.game_id,
+        )
     }
 }
+
+impl Error for UserIsNotAllowedError {}
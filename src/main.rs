@@ -2,6 +2,12 @@ use teloxide::{dispatching::dialogue::InMemStorage, prelude::*};
 
 mod errors;
 
+mod draw;
+
+mod outbox;
+
+mod scheduler;
+
 mod utils;
 
 mod runner;
@@ -21,6 +27,9 @@ async fn main() {
 
     let runner = Runner::new(DB_PATH).unwrap();
 
+    tokio::spawn(outbox::run(bot.clone(), runner.clone()));
+    tokio::spawn(scheduler::run(runner.clone()));
+
     Dispatcher::builder(bot, schema())
         .dependencies(dptree::deps![runner.clone(), InMemStorage::<State>::new()])
         .enable_ctrlc_handler()
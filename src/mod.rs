@@ -1,4 +1,7 @@
+pub mod draw;
 pub mod errors;
+pub mod outbox;
 pub mod runner;
+pub mod scheduler;
 pub mod scheme;
 pub mod utils;
@@ -0,0 +1,60 @@
+use crate::{runner::Runner, utils::*};
+use std::time::Duration;
+use teloxide::{
+    payloads::SendMessageSetters,
+    prelude::{ChatId, Requester},
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+    Bot, RequestError,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// sends messages from the outbox forever, retrying the ones that failed
+pub async fn run(bot: Bot, runner: Runner) {
+    loop {
+        deliver(&bot, &runner).await;
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn deliver(bot: &Bot, runner: &Runner) {
+    let mut settled_games: Vec<GameId> = Vec::new();
+
+    for message in runner.due_messages(now()) {
+        let mut request = bot.send_message(ChatId(message.recipient.0), message.text.clone());
+        if !message.buttons.is_empty() {
+            request = request
+                .reply_markup(InlineKeyboardMarkup::new([message.buttons.iter().map(
+                    |(text, action)| InlineKeyboardButton::callback(text, action.to_string()),
+                )]));
+        }
+
+        let settled = match request.await {
+            Ok(_) => runner.mark_message_sent(&message.id).map(|_| true),
+            // telegram refused the message (e.g. user blocked the bot), retrying won't help
+            Err(RequestError::Api(error)) => {
+                runner.mark_message_failed(&message.id, error.to_string(), true)
+            }
+            Err(error) => runner.mark_message_failed(&message.id, error.to_string(), false),
+        };
+
+        match settled {
+            Ok(true) => {
+                if let Some(game_id) = message.game_id {
+                    if !settled_games.contains(&game_id) {
+                        settled_games.push(game_id);
+                    }
+                }
+            }
+            Ok(false) => {}
+            Err(error) => log::error!("Unable to update message {}: {error}", message.id.0),
+        }
+    }
+
+    // report is sent only once since the last message of a game is settled only once
+    for game_id in settled_games {
+        if let Err(error) = runner.report_delivery(&game_id) {
+            log::error!("Unable to enqueue delivery report for game {game_id}: {error}");
+        }
+    }
+}
@@ -1,9 +1,25 @@
+use crate::draw::{self, Constraints, NoRepeats, NoSelfPresent, Outcome};
 use crate::errors::*;
 use crate::utils::{UserId, *};
-use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng};
-use sled::{Batch, Db};
-use std::{error::Error, iter::zip};
+use rand::{distributions::Alphanumeric, seq::SliceRandom, Rng};
+use sled::{
+    transaction::{self, TransactionError},
+    Batch, Db,
+};
+use std::error::Error;
+
+// how many previous editions of a game are taken into account to avoid repeating pairs
+const REPEAT_AVOIDANCE_DEPTH: usize = 3;
+
+// keys of all games start with it, see `GameId::to_key`
+const GAME_PREFIX: &str = "GameId(";
+// keys of all outbox messages start with it, see `OutboxId::to_key`
+const OUTBOX_PREFIX: &str = "OutboxId(";
+// after that many attempts message is considered undeliverable
+const MAX_DELIVERY_ATTEMPTS: u32 = 6;
+const RETRY_BASE_DELAY_SECS: u64 = 30;
+// telegram allows up to 64 characters in /start payload
+const INVITE_TOKEN_LENGTH: usize = 16;
 
 #[derive(Clone)]
 pub struct Runner {
@@ -11,11 +27,17 @@ pub struct Runner {
 }
 
 impl Runner {
-    pub fn new(db_path: &str) -> Result<Self, Box<dyn Error>> {
+    pub fn new(db_path: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
         let database = sled::open(db_path)?;
-        Ok(Self { database })
+        let runner = Self { database };
+        runner.assign_missing_codes()?;
+        Ok(runner)
     }
-    pub fn new_user(&self, id: UserId, username: String) -> Result<(), Box<dyn Error>> {
+    pub fn new_user(
+        &self,
+        id: UserId,
+        username: String,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
         // There's no need to check for id collision since telegram already does it
         let response = self.database.get(id.to_key())?;
 
@@ -33,6 +55,8 @@ impl Runner {
             admin_games: vec![],
             active_games: vec![],
             pending_games: vec![],
+            wishlists: vec![],
+            co_admin_games: vec![],
         };
 
         match self.database.insert(id.to_key(), user.to_ron().as_str()) {
@@ -40,10 +64,17 @@ impl Runner {
             Err(error) => Err(Box::new(error)),
         }
     }
-    pub fn new_game(&self, admin: UserId, name: String) -> Result<GameId, Box<dyn Error>> {
+    pub fn new_game(
+        &self,
+        admin: UserId,
+        name: String,
+        draw_mode: DrawMode,
+        budget: Option<Budget>,
+        admin_participates: bool,
+    ) -> Result<GameId, Box<dyn Error + Send + Sync>> {
         let mut rng = rand::thread_rng();
         let mut id = GameId(rng.gen());
-        while !self
+        while self
             .database
             .contains_key(ron::to_string(&id).unwrap())
             .unwrap()
@@ -55,33 +86,150 @@ impl Runner {
             id,
             name,
             admin,
-            active_users: vec![],
+            active_users: match admin_participates {
+                true => vec![admin],
+                false => vec![],
+            },
             pending_users: vec![],
+            exclusions: vec![],
+            draw_mode,
+            previous_game: None,
+            status: GameStatus::Open,
+            budget,
+            draw_at: None,
+            registration_closes_at: None,
+            party_at: None,
+            reminders: vec![],
+            invite_token: None,
+            code: Some(self.unused_game_code()?),
+            join_policy: JoinPolicy::Manual,
+            rejections: vec![],
+            co_admins: vec![],
+            transfer_to: None,
+            invited_users: vec![],
+            recurrence: None,
         };
 
-        self.user_add_admin_game(&admin, &game.id)?;
+        self.user_add_admin_game(&admin, &game.id, admin_participates)?;
 
         // we don't check for existing game since we've already checked for it previously
-        self.database.insert(id.to_key(), game.to_ron().as_str())?;
+        let mut batch = Batch::default();
+        if let Some(code) = &game.code {
+            batch.insert(code.to_key().as_str(), id.to_key().as_str());
+        }
+        batch.insert(id.to_key().as_str(), game.to_ron().as_str());
+        self.database.apply_batch(batch)?;
 
         Ok(id)
     }
+    fn unused_game_code(&self) -> Result<GameCode, Box<dyn Error + Send + Sync>> {
+        let mut rng = rand::thread_rng();
+        let mut generate = || {
+            GameCode(
+                (0..GAME_CODE_LENGTH)
+                    .map(|_| char::from(*GAME_CODE_ALPHABET.choose(&mut rng).unwrap()))
+                    .collect(),
+            )
+        };
+        let mut code = generate();
+        while self.database.contains_key(code.to_key())? {
+            code = generate();
+        }
+        Ok(code)
+    }
+    fn assign_missing_codes(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        for game in self.games().filter(|game| game.code.is_none()) {
+            let code = self.unused_game_code()?;
+            let game_id = game.id;
+            let read_game = game.to_ron();
+            let new_game = Game {
+                code: Some(code.clone()),
+                ..game
+            };
+
+            let mut batch = Batch::default();
+            batch.insert(code.to_key().as_str(), game_id.to_key().as_str());
+            batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+            self.apply_game_batch(&game_id, &read_game, batch)?;
+        }
+
+        Ok(())
+    }
+    /// finds the game by either its code or its full id
+    pub fn find_game(&self, text: &str) -> Option<GameId> {
+        if let Ok(code) = text.parse::<GameCode>() {
+            if let Some(game_id) = self.database.get(code.to_key()).unwrap() {
+                return Some(GameId::from(game_id));
+            }
+        }
+
+        text.parse::<GameId>()
+            .ok()
+            .filter(|game_id| self.database.contains_key(game_id.to_key()).unwrap())
+    }
+    /// returns token of the invite link to the game, generating it on first use
+    pub fn invite_token(
+        &self,
+        game_id: &GameId,
+    ) -> Result<InviteToken, Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+
+        if let Some(token) = game.invite_token {
+            return Ok(token);
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut generate = || {
+            InviteToken(
+                (&mut rng)
+                    .sample_iter(&Alphanumeric)
+                    .take(INVITE_TOKEN_LENGTH)
+                    .map(char::from)
+                    .collect(),
+            )
+        };
+        let mut token = generate();
+        while self.database.contains_key(token.to_key())? {
+            token = generate();
+        }
+
+        let new_game = Game {
+            invite_token: Some(token.clone()),
+            ..game
+        };
+
+        let mut batch = Batch::default();
+        batch.insert(token.to_key().as_str(), game_id.to_key().as_str());
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(token)
+    }
+    pub fn find_game_by_invite(&self, token: &InviteToken) -> Option<GameId> {
+        self.database.get(token.to_key()).unwrap().map(GameId::from)
+    }
     fn user_add_admin_game(
         &self,
         user_id: &UserId,
         game_id: &GameId,
-    ) -> Result<(), Box<dyn Error>> {
+        participates: bool,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
         match self.get_user(user_id) {
             Some(user) => {
                 let mut admin_games = user.admin_games;
                 admin_games.push(*game_id);
+                let mut active_games = user.active_games;
+                if participates {
+                    active_games.push(*game_id);
+                }
 
                 let new_user = User {
-                    id: user.id,
-                    username: user.username,
                     admin_games,
-                    active_games: user.active_games,
-                    pending_games: user.pending_games,
+                    active_games,
+                    ..user
                 };
                 self.database
                     .insert(user_id.to_key(), new_user.to_ron().as_str())?;
@@ -100,15 +248,12 @@ impl Runner {
         &self,
         user_id: &UserId,
         new_username: String,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
         match self.get_user(user_id) {
             Some(user) => {
                 let new_user = User {
-                    id: user.id,
                     username: new_username,
-                    admin_games: user.admin_games,
-                    active_games: user.active_games,
-                    pending_games: user.pending_games,
+                    ..user
                 };
                 self.database
                     .insert(user_id.to_key(), new_user.to_ron().as_str())?;
@@ -117,120 +262,966 @@ impl Runner {
             None => Err(Box::new(UserDoesNotExistError { id: *user_id })),
         }
     }
-    fn remove_game_from_user(
+    pub fn set_wishlist(
         &self,
         user_id: &UserId,
         game_id: &GameId,
-    ) -> Result<(), Box<dyn Error>> {
-        match self.get_user(user_id) {
-            Some(user) => {
-                let mut admin_games = user.admin_games;
-                admin_games.retain(|id| id != game_id);
-                let mut active_games = user.active_games;
-                active_games.retain(|id| id != game_id);
-                let mut pending_games = user.pending_games;
-                pending_games.retain(|id| id != game_id);
-
-                let new_user = User {
-                    id: user.id,
-                    username: user.username,
-                    admin_games,
-                    active_games,
-                    pending_games,
-                };
-                self.database
-                    .insert(user_id.to_key(), new_user.to_ron().as_str())?;
-                Ok(())
-            }
-            None => Err(Box::new(UserDoesNotExistError { id: *user_id })),
+        wishlist: Wishlist,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(user) = self.get_user(user_id) else {
+            return Err(Box::new(UserDoesNotExistError { id: *user_id }));
+        };
+        if !user.active_games.contains(game_id) && !user.pending_games.contains(game_id) {
+            return Err(Box::new(UserIsNotInGameError {
+                user_id: *user_id,
+                game_id: *game_id,
+            }));
         }
+
+        let mut wishlists = user.wishlists;
+        wishlists.retain(|(id, _)| id != game_id);
+        wishlists.push((*game_id, wishlist));
+
+        let new_user = User { wishlists, ..user };
+        self.database
+            .insert(user_id.to_key(), new_user.to_ron().as_str())?;
+
+        Ok(())
     }
-    /// returns vector of pairs (userid, message to send)
-    pub fn run_game(&self, game_id: GameId) -> Result<Vec<(UserId, String)>, Box<dyn Error>> {
+    /// distributes presents and puts messages about it to the outbox
+    pub fn run_game(&self, game_id: GameId) -> Result<(), Box<dyn Error + Send + Sync>> {
         let Some(game) = self.get_game(&game_id) else {
             return Err(Box::new(GameDoesNotExistError { id: game_id }));
         };
 
-        self.database.remove(game_id.to_key())?;
-
-        self.remove_game_from_user(&game.admin, &game_id)?;
-        for user in game.active_users {
-            self.remove_game_from_user(&user, &game_id)?;
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: game_id }));
         }
-        for user in game.pending_users {
-            self.remove_game_from_user(&user, &game_id)?;
+
+        if game.active_users.len() < 2 {
+            return Err(Box::new(NotEnoughParticipantsError { game_id }));
         }
+        let read_game = game.to_ron();
 
-        let game_name = game.name;
-        let users = game.active_users;
+        let distribution = match self.draw_game(&game) {
+            Outcome::Assigned(distribution) => distribution,
+            Outcome::Infeasible => {
+                return Err(Box::new(NoValidAssignmentError { game_id }));
+            }
+        };
+
+        let game_name = game.name.clone();
+        let budget = match &game.budget {
+            Some(budget) => format!("The budget is {budget}.\n\n"),
+            None => String::new(),
+        };
         let mut messages = Vec::new();
 
-        if users.len() <= 1 {
-            for user_id in users {
-                messages.push((
-                    user_id,
-                    "It looks like there's only one participant :(\
-                    We can't run this game."
-                        .to_owned(),
-                ));
-            }
+        for (presenter, recipient) in &distribution {
+            let Some(presenter) = self.get_user(presenter) else {
+                return Err(Box::new(UserDoesNotExistError { id: *presenter }));
+            };
+            let presenter_name = presenter.username;
+            let Some(recipient) = self.get_user(recipient) else {
+                return Err(Box::new(UserDoesNotExistError { id: *recipient }));
+            };
+            let wishlist = match recipient.wishlist(&game_id) {
+                Some(wishlist) if !wishlist.is_empty() => {
+                    format!("Here's their wishlist:\n{wishlist}\n")
+                }
+                _ => String::new(),
+            };
+            let recipient_name = recipient.username;
+
             messages.push((
-                game.admin,
-                "All messages have been sent successfully!\
-                It seems like there was less than 2 players so there'll be no presents :("
-                    .to_owned(),
+                presenter.id,
+                format!(
+                    "Ho Ho Ho, {presenter_name}!\n\n\
+                     As a result of participating in game {game_name}. It looks like you have to prepare a present for {recipient_name}!\n\n\
+                     {wishlist}\
+                     {budget}\
+                     You can always use /mysanta to see it again.\n\n\
+                     Have a happy new year, your secret santa bot."
+                ),
             ));
-        } else {
-            for (presenter, recipient) in Self::distribute_presents(users) {
-                let Some(presenter) = self.get_user(&presenter) else {
-                    return Err(Box::new(UserDoesNotExistError { id: presenter }));
+        }
+
+        let assignments = Assignments {
+            game_id,
+            game_name,
+            previous_game: game.previous_game,
+            pairs: distribution,
+        };
+
+        let mut batch = Batch::default();
+
+        // users that weren't accepted before the draw don't take part in it
+        for user_id in &game.pending_users {
+            let Some(user) = self.get_user(user_id) else {
+                return Err(Box::new(UserDoesNotExistError { id: *user_id }));
+            };
+            let mut pending_games = user.pending_games;
+            pending_games.retain(|id| id != &game_id);
+
+            let new_user = User {
+                pending_games,
+                ..user
+            };
+            batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
+        }
+
+        let new_game = Game {
+            pending_users: vec![],
+            status: GameStatus::Drawn,
+            draw_at: None,
+            ..game
+        };
+
+        batch.insert(
+            AssignmentsId(game_id).to_key().as_str(),
+            assignments.to_ron().as_str(),
+        );
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        // messages are stored together with the draw, so they can't be lost if sending fails
+        self.add_messages_to_batch(&mut batch, Some(game_id), messages)?;
+
+        // scheduled and manual draws can run at the same time, only one of them may succeed
+        self.apply_game_batch(&game_id, &read_game, batch)?;
+
+        // the draw has already happened, so it's not its error if the next edition can't be created
+        if new_game.recurrence.is_some() {
+            if let Err(error) = self.spawn_next_edition(&game_id) {
+                log::error!("Unable to create next edition of game {game_id}: {error}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// returns (game, recipient) for every drawn game user has prepared a present in
+    pub fn get_recipients(&self, user_id: &UserId) -> Vec<(Game, UserId)> {
+        let Some(user) = self.get_user(user_id) else {
+            return vec![];
+        };
+
+        user.active_games
+            .iter()
+            .filter_map(|game_id| {
+                let game = self.get_game(game_id)?;
+                let recipient = self.get_recipient(game_id, user_id)?;
+                Some((game, recipient))
+            })
+            .collect()
+    }
+    /// returns the user `user_id` prepares a present for in a drawn game
+    pub fn get_recipient(&self, game_id: &GameId, user_id: &UserId) -> Option<UserId> {
+        self.get_assignments(game_id)?
+            .pairs
+            .into_iter()
+            .find(|(presenter, _)| presenter == user_id)
+            .map(|(_, recipient)| recipient)
+    }
+    /// returns the user who prepares a present for `user_id` in a drawn game
+    pub fn get_santa(&self, game_id: &GameId, user_id: &UserId) -> Option<UserId> {
+        self.get_assignments(game_id)?
+            .pairs
+            .into_iter()
+            .find(|(_, recipient)| recipient == user_id)
+            .map(|(presenter, _)| presenter)
+    }
+    // pairs of the most recent editions are the last to be allowed again if the draw is impossible
+    fn draw_game(&self, game: &Game) -> Outcome {
+        let history = self.previous_pairs(game.previous_game);
+
+        for depth in (0..=history.len()).rev() {
+            let constraints = game.exclusions.iter().fold(
+                Constraints::default()
+                    .with(NoSelfPresent)
+                    .with(NoRepeats(history[..depth].concat())),
+                |constraints, exclusion| constraints.with(*exclusion),
+            );
+
+            let outcome = match game.draw_mode {
+                DrawMode::Pairs => draw::assign(&game.active_users, &constraints),
+                DrawMode::Chain => draw::single_cycle(&game.active_users, &constraints),
+            };
+            if outcome != Outcome::Infeasible {
+                return outcome;
+            }
+        }
+
+        Outcome::Infeasible
+    }
+    /// returns pairs of up to `REPEAT_AVOIDANCE_DEPTH` previous editions, the most recent first
+    fn previous_pairs(&self, mut previous_game: Option<GameId>) -> Vec<Vec<(UserId, UserId)>> {
+        let mut history = vec![];
+        while let Some(game_id) = previous_game {
+            let Some(assignments) = self.get_assignments(&game_id) else {
+                break;
+            };
+            if history.len() == REPEAT_AVOIDANCE_DEPTH {
+                break;
+            }
+            history.push(assignments.pairs);
+            previous_game = assignments.previous_game;
+        }
+        history
+    }
+    pub fn get_assignments(&self, game_id: &GameId) -> Option<Assignments> {
+        self.database
+            .get(AssignmentsId(*game_id).to_key())
+            .unwrap()
+            .map(Assignments::from)
+    }
+    /// `user_id` has to manage the previous game too, otherwise its pairs could be guessed from the draw
+    pub fn link_previous_game(
+        &self,
+        user_id: &UserId,
+        game_id: &GameId,
+        previous_game: &GameId,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+        let Some(previous) = self.get_game(previous_game) else {
+            return Err(Box::new(GameDoesNotExistError { id: *previous_game }));
+        };
+        if !previous.allows(user_id, Permission::EditSettings) {
+            return Err(Box::new(UserIsNotAllowedError {
+                user_id: *user_id,
+                game_id: *previous_game,
+            }));
+        }
+        if self.get_assignments(previous_game).is_none() {
+            return Err(Box::new(GameWasNotDrawnError { id: *previous_game }));
+        }
+
+        let new_game = Game {
+            previous_game: Some(*previous_game),
+            ..game
+        };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn set_draw_mode(
+        &self,
+        game_id: &GameId,
+        draw_mode: DrawMode,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+
+        let new_game = Game { draw_mode, ..game };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn set_budget(
+        &self,
+        game_id: &GameId,
+        budget: Option<Budget>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+
+        let new_game = Game { budget, ..game };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn set_draw_time(
+        &self,
+        game_id: &GameId,
+        draw_at: Option<DateTime>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+
+        let read_game = game.to_ron();
+        let new_game = Game { draw_at, ..game };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// this game becomes the first edition drawn at or after `first_draw` that's still ahead
+    pub fn set_recurrence(
+        &self,
+        game_id: &GameId,
+        first_draw: Option<DateTime>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+
+        let read_game = game.to_ron();
+        let recurrence = first_draw.map(|first_draw| Recurrence::new(first_draw, now()));
+        // the draw of this edition is scheduled together with the recurrence and dropped with it
+        let new_game = Game {
+            draw_at: recurrence.map(|recurrence| recurrence.draw_at()),
+            recurrence,
+            ..game
+        };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// closes registration right away if `at` is `None`
+    pub fn lock_game(
+        &self,
+        game_id: &GameId,
+        at: Option<DateTime>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+        let read_game = game.to_ron();
+
+        let new_game = match at {
+            Some(at) => Game {
+                registration_closes_at: Some(at),
+                ..game
+            },
+            None => Game {
+                status: GameStatus::Closed,
+                ..game
+            },
+        };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// opens registration and drops its deadline
+    pub fn unlock_game(&self, game_id: &GameId) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+
+        let new_game = Game {
+            status: GameStatus::Open,
+            registration_closes_at: None,
+            ..game
+        };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn set_join_policy(
+        &self,
+        game_id: &GameId,
+        join_policy: JoinPolicy,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+
+        let new_game = Game {
+            join_policy,
+            ..game
+        };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn set_party_time(
+        &self,
+        game_id: &GameId,
+        party_at: Option<DateTime>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+
+        let new_game = Game { party_at, ..game };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn add_reminder(
+        &self,
+        game_id: &GameId,
+        event: ReminderEvent,
+        hours_before: u32,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+
+        let mut reminders = game.reminders;
+        if !reminders
+            .iter()
+            .any(|reminder| reminder.event == event && reminder.hours_before == hours_before)
+        {
+            reminders.push(Reminder {
+                event,
+                hours_before,
+                sent_for: None,
+            });
+        }
+
+        let new_game = Game { reminders, ..game };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn remove_reminders(
+        &self,
+        game_id: &GameId,
+        event: ReminderEvent,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+
+        let mut reminders = game.reminders;
+        reminders.retain(|reminder| reminder.event != event);
+
+        let new_game = Game { reminders, ..game };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// puts reminders that are due at `now` to the outbox
+    pub fn send_due_reminders(&self, now: u64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        for game in self.games() {
+            if game.status == GameStatus::Archived {
+                continue;
+            }
+
+            let mut messages = Vec::new();
+            let mut reminders = game.reminders.clone();
+            for reminder in reminders.iter_mut() {
+                // registration and draw reminders make no sense once presents are distributed
+                if game.is_drawn() && reminder.event != ReminderEvent::Party {
+                    continue;
+                }
+                let Some(event_time) = game.event_time(reminder.event) else {
+                    continue;
                 };
-                let presenter_name = presenter.username;
-                let Some(recipient) = self.get_user(&recipient) else {
-                    return Err(Box::new(UserDoesNotExistError { id: recipient }));
+                let remind_at = event_time
+                    .timestamp
+                    .saturating_sub(reminder.hours_before as u64 * 3600);
+                if now < remind_at || now >= event_time.timestamp {
+                    continue;
+                }
+                if reminder.sent_for == Some(event_time.timestamp) {
+                    continue;
+                }
+                reminder.sent_for = Some(event_time.timestamp);
+
+                let game_name = &game.name;
+                let text = match reminder.event {
+                    ReminderEvent::RegistrationCloses => format!(
+                        "Reminder: registration to game {game_name} closes at {event_time}."
+                    ),
+                    ReminderEvent::Draw => format!(
+                        "Reminder: presents in game {game_name} will be distributed at {event_time}.\n\
+                        Don't forget to update your /wishlist!"
+                    ),
+                    ReminderEvent::Party => format!(
+                        "Reminder: gift exchange party of game {game_name} is at {event_time}.\n\
+                        Don't forget your present!"
+                    ),
                 };
-                let recipient_name = recipient.username;
-
-                messages.push((
-                    presenter.id,
-                                format!(
-                                    "Ho Ho Ho, {presenter_name}!\n\n\
-                                     As a result of participating in game {game_name}. It looks like you have to prepare a present for {recipient_name}!\n\n\
-                                     Have a happy new year, your secret santa bot."
-                                ),
-                ));
+                // pending users can't take part in the draw or the party until they're accepted
+                let mut recipients = game.active_users.clone();
+                if reminder.event == ReminderEvent::RegistrationCloses {
+                    recipients.extend(game.pending_users.iter());
+                }
+                messages.extend(
+                    recipients
+                        .into_iter()
+                        .map(|user_id| (user_id, text.clone())),
+                );
+            }
+
+            if reminders == game.reminders {
+                continue;
+            }
+
+            let game_id = game.id;
+            let read_game = game.to_ron();
+            let new_game = Game { reminders, ..game };
+
+            let mut batch = Batch::default();
+            batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+            self.add_messages_to_batch(&mut batch, None, messages)?;
+            match self.apply_game_batch(&game_id, &read_game, batch) {
+                // reminders of the changed game will be checked again on the next run
+                Err(error) if error.is::<GameWasChangedError>() => continue,
+                result => result?,
             }
-            messages.push((
-                game.admin,
-                "All messages have been sent successfully!".to_owned(),
-            ));
         }
 
-        Ok(messages)
+        Ok(())
     }
+    /// applies the batch only if the game is still the same as `read_game`,
+    /// so concurrent updates of the game can't overwrite each other
+    fn apply_game_batch(
+        &self,
+        game_id: &GameId,
+        read_game: &str,
+        batch: Batch,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let key = game_id.to_key();
+        let result = self.database.transaction(|tree| {
+            let current = tree
+                .get(key.as_str())?
+                .map(|value| Game::from(value).to_ron());
+            if current.as_deref() != Some(read_game) {
+                return transaction::abort(());
+            }
+            tree.apply_batch(&batch)?;
+            Ok(())
+        });
 
-    fn distribute_presents(users: Vec<UserId>) -> Vec<(UserId, UserId)> {
-        let mut rng = thread_rng();
-        let mut shuffled;
-        loop {
-            shuffled = users.clone();
-            shuffled.shuffle(&mut rng);
+        match result {
+            Ok(()) => Ok(()),
+            Err(TransactionError::Abort(())) => Err(Box::new(GameWasChangedError { id: *game_id })),
+            Err(TransactionError::Storage(error)) => Err(Box::new(error)),
+        }
+    }
+    fn games(&self) -> impl Iterator<Item = Game> {
+        self.database
+            .scan_prefix(GAME_PREFIX)
+            .values()
+            .map(|value| Game::from(value.unwrap()))
+    }
+    /// returns open games whose registration deadline has passed at `now`
+    pub fn due_registration_closings(&self, now: u64) -> Vec<Game> {
+        self.games()
+            .filter(|game| game.status == GameStatus::Open && game.is_locked(now))
+            .collect()
+    }
+    /// returns games that should be drawn automatically at `now`
+    pub fn due_draws(&self, now: u64) -> Vec<Game> {
+        self.games()
+            .filter(|game| {
+                !game.is_drawn() && game.draw_at.is_some_and(|draw_at| draw_at.timestamp <= now)
+            })
+            .collect()
+    }
+    pub fn add_exclusion(
+        &self,
+        game_id: &GameId,
+        exclusion: Exclusion,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
 
-            if !zip(users.clone(), shuffled.clone()).any(|(a, b)| a == b) {
-                break;
+        let (first, second) = match exclusion {
+            Exclusion::OneWay {
+                presenter,
+                recipient,
+            } => (presenter, recipient),
+            Exclusion::BothWays(a, b) => (a, b),
+        };
+        for user_id in [first, second] {
+            if !game.active_users.contains(&user_id) && !game.pending_users.contains(&user_id) {
+                return Err(Box::new(UserIsNotInGameError {
+                    user_id,
+                    game_id: *game_id,
+                }));
             }
         }
-        zip(users, shuffled).collect()
+
+        let mut exclusions = game.exclusions;
+        if !exclusions.contains(&exclusion) {
+            exclusions.push(exclusion);
+        }
+
+        let new_game = Game { exclusions, ..game };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    // doesn't throw an error if there's no such exclusion
+    pub fn remove_exclusion(
+        &self,
+        game_id: &GameId,
+        exclusion: &Exclusion,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+
+        let mut exclusions = game.exclusions;
+        exclusions.retain(|elem| elem != exclusion);
+
+        let new_game = Game { exclusions, ..game };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
     }
 
+    pub fn archive_game(&self, game_id: &GameId) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.status != GameStatus::Drawn {
+            return Err(Box::new(GameWasNotDrawnError { id: *game_id }));
+        }
+
+        let new_game = Game {
+            status: GameStatus::Archived,
+            ..game
+        };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// removes a game that wasn't drawn yet from everyone who had anything to do with it
+    pub fn delete_game(&self, game_id: &GameId) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+
+        let mut user_ids = vec![game.admin];
+        for user_id in game
+            .co_admins
+            .iter()
+            .map(|co_admin| co_admin.user_id)
+            .chain(game.active_users.iter().copied())
+            .chain(game.pending_users.iter().copied())
+        {
+            if !user_ids.contains(&user_id) {
+                user_ids.push(user_id);
+            }
+        }
+
+        let game_name = &game.name;
+        let mut batch = Batch::default();
+        for user_id in user_ids {
+            let Some(user) = self.get_user(&user_id) else {
+                return Err(Box::new(UserDoesNotExistError { id: user_id }));
+            };
+
+            let mut admin_games = user.admin_games;
+            admin_games.retain(|id| id != game_id);
+            let mut co_admin_games = user.co_admin_games;
+            co_admin_games.retain(|id| id != game_id);
+            let mut active_games = user.active_games;
+            active_games.retain(|id| id != game_id);
+            let mut pending_games = user.pending_games;
+            pending_games.retain(|id| id != game_id);
+            let mut wishlists = user.wishlists;
+            wishlists.retain(|(id, _)| id != game_id);
+
+            let new_user = User {
+                admin_games,
+                co_admin_games,
+                active_games,
+                pending_games,
+                wishlists,
+                ..user
+            };
+            batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
+
+            if user_id != game.admin {
+                self.add_messages_to_batch(
+                    &mut batch,
+                    None,
+                    vec![(
+                        user_id,
+                        format!("Game {game_name} was cancelled by its admin."),
+                    )],
+                )?;
+            }
+        }
+
+        if let Some(code) = &game.code {
+            batch.remove(code.to_key().as_str());
+        }
+        if let Some(token) = &game.invite_token {
+            batch.remove(token.to_key().as_str());
+        }
+        for message in self.game_messages(game_id) {
+            batch.remove(message.id.to_key().as_str());
+        }
+        batch.remove(game_id.to_key().as_str());
+
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+
+    fn add_messages_to_batch(
+        &self,
+        batch: &mut Batch,
+        game_id: Option<GameId>,
+        messages: Vec<(UserId, String)>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        for (recipient, text) in messages {
+            self.add_message_with_buttons_to_batch(batch, game_id, recipient, text, vec![])?;
+        }
+        Ok(())
+    }
+    fn add_message_with_buttons_to_batch(
+        &self,
+        batch: &mut Batch,
+        game_id: Option<GameId>,
+        recipient: UserId,
+        text: String,
+        buttons: Vec<(String, Action)>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let id = OutboxId(self.database.generate_id()?);
+        let message = OutboxMessage {
+            id,
+            game_id,
+            recipient,
+            text,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt: now(),
+            buttons,
+        };
+        batch.insert(id.to_key().as_str(), message.to_ron().as_str());
+        Ok(())
+    }
+    pub fn enqueue_messages(
+        &self,
+        game_id: Option<GameId>,
+        messages: Vec<(UserId, String)>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut batch = Batch::default();
+        self.add_messages_to_batch(&mut batch, game_id, messages)?;
+        self.database.apply_batch(batch)?;
+        Ok(())
+    }
+    fn outbox(&self) -> impl Iterator<Item = OutboxMessage> {
+        self.database
+            .scan_prefix(OUTBOX_PREFIX)
+            .values()
+            .map(|value| OutboxMessage::from(value.unwrap()))
+    }
+    /// returns pending messages that should be sent at `now`, oldest first
+    pub fn due_messages(&self, now: u64) -> Vec<OutboxMessage> {
+        let mut messages: Vec<OutboxMessage> = self
+            .outbox()
+            .filter(|message| {
+                message.status == DeliveryStatus::Pending && message.next_attempt <= now
+            })
+            .collect();
+        messages.sort_by_key(|message| message.id.0);
+        messages
+    }
+    pub fn game_messages(&self, game_id: &GameId) -> Vec<OutboxMessage> {
+        self.outbox()
+            .filter(|message| message.game_id == Some(*game_id))
+            .collect()
+    }
+    fn get_message(&self, id: &OutboxId) -> Option<OutboxMessage> {
+        self.database
+            .get(id.to_key())
+            .unwrap()
+            .map(OutboxMessage::from)
+    }
+    /// messages about a game are kept until its delivery report is sent, others are removed right away
+    pub fn mark_message_sent(&self, id: &OutboxId) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(message) = self.get_message(id) else {
+            return Err(Box::new(MessageDoesNotExistError { id: *id }));
+        };
+
+        if message.game_id.is_none() {
+            self.database.remove(id.to_key())?;
+            return Ok(());
+        }
+
+        let new_message = OutboxMessage {
+            status: DeliveryStatus::Sent,
+            attempts: message.attempts + 1,
+            ..message
+        };
+        self.database
+            .insert(id.to_key(), new_message.to_ron().as_str())?;
+
+        Ok(())
+    }
+    /// returns true if there'll be no more attempts to send this message
+    pub fn mark_message_failed(
+        &self,
+        id: &OutboxId,
+        reason: String,
+        permanent: bool,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let Some(message) = self.get_message(id) else {
+            return Err(Box::new(MessageDoesNotExistError { id: *id }));
+        };
+
+        let attempts = message.attempts + 1;
+        let gave_up = permanent || attempts >= MAX_DELIVERY_ATTEMPTS;
+
+        // nobody is told about undelivered messages that aren't about a game
+        if gave_up && message.game_id.is_none() {
+            log::warn!(
+                "Unable to deliver message {} to user {}: {reason}",
+                id.0,
+                message.recipient
+            );
+            self.database.remove(id.to_key())?;
+            return Ok(true);
+        }
+
+        let new_message = OutboxMessage {
+            status: match gave_up {
+                true => DeliveryStatus::Failed { reason },
+                false => DeliveryStatus::Pending,
+            },
+            attempts,
+            // exponential backoff: 30s, 1m, 2m, 4m, ...
+            next_attempt: now() + RETRY_BASE_DELAY_SECS * 2u64.pow(attempts - 1),
+            ..message
+        };
+        self.database
+            .insert(id.to_key(), new_message.to_ron().as_str())?;
+
+        Ok(gave_up)
+    }
+    /// sends the report to the admin once every message about the game is either delivered or failed,
+    /// delivered messages aren't needed after that, failed ones are kept for /info
+    pub fn report_delivery(&self, game_id: &GameId) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let messages = self.game_messages(game_id);
+
+        if messages
+            .iter()
+            .any(|message| message.status == DeliveryStatus::Pending)
+        {
+            return Ok(());
+        }
+
+        let game_name = game.name;
+        let failed: Vec<String> = messages
+            .iter()
+            .filter_map(|message| match &message.status {
+                DeliveryStatus::Failed { reason } => {
+                    let username = self
+                        .get_user(&message.recipient)
+                        .map(|user| user.username)
+                        .unwrap_or_else(|| message.recipient.to_string());
+                    Some(format!("{username}: {reason}"))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let report = match failed.len() {
+            0 => format!("All messages about game {game_name} have been delivered successfully!"),
+            _ => format!(
+                "Some messages about game {game_name} couldn't be delivered:\n\n{}\n\n\
+                Please ask these participants to check that they haven't blocked the bot \
+                and use /mysanta to see who they're preparing a present for.",
+                failed.join("\n")
+            ),
+        };
+
+        let mut batch = Batch::default();
+        messages
+            .iter()
+            .filter(|message| message.status == DeliveryStatus::Sent)
+            .for_each(|message| batch.remove(message.id.to_key().as_str()));
+        self.add_messages_to_batch(&mut batch, None, vec![(game.admin, report)])?;
+        self.database.apply_batch(batch)?;
+
+        Ok(())
+    }
+    /// join policy of the game decides if user waits for admin or becomes a participant right away
     pub fn add_user_to_pending(
         &self,
         user_id: &UserId,
         game_id: &GameId,
-    ) -> Result<(), Box<dyn Error>> {
+        request: JoinRequest,
+    ) -> Result<JoinOutcome, Box<dyn Error + Send + Sync>> {
         match self.get_game(game_id) {
             Some(game) => match self.get_user(user_id) {
                 Some(user) => {
+                    let read_game = game.to_ron();
+                    if game.is_drawn() {
+                        return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+                    }
+                    if game.is_locked(now()) {
+                        return Err(Box::new(GameIsLockedError { id: *game_id }));
+                    }
                     if game.pending_users.contains(user_id) || game.active_users.contains(user_id) {
                         return Err(Box::new(UserIsAlreadyInGameError {
                             user_id: *user_id,
@@ -243,50 +1234,183 @@ impl Runner {
                             game_id: *game_id,
                         }));
                     }
+                    if game
+                        .rejections
+                        .iter()
+                        .any(|rejection| rejection.user_id == *user_id)
+                    {
+                        return Err(Box::new(UserIsRejectedError {
+                            user_id: *user_id,
+                            game_id: *game_id,
+                        }));
+                    }
 
-                    let mut pending_games = user.pending_games;
-                    pending_games.push(*game_id);
-
-                    let new_user = User {
-                        id: user.id,
-                        username: user.username,
-                        admin_games: user.admin_games,
-                        active_games: user.active_games,
-                        pending_games,
+                    let outcome = match (&game.join_policy, request) {
+                        // admin doesn't have to approve themselves
+                        _ if game.admin == *user_id => JoinOutcome::Active,
+                        (JoinPolicy::Manual, _) => JoinOutcome::Pending,
+                        (JoinPolicy::AutoAccept, _) => JoinOutcome::Active,
+                        (JoinPolicy::Password(password), JoinRequest::Password(attempt))
+                            if *password == attempt =>
+                        {
+                            JoinOutcome::Active
+                        }
+                        (JoinPolicy::Password(_), _) => {
+                            return Err(Box::new(WrongPasswordError { id: *game_id }));
+                        }
+                        (JoinPolicy::InviteOnly, JoinRequest::Invite) => JoinOutcome::Active,
+                        (JoinPolicy::InviteOnly, _) => {
+                            return Err(Box::new(GameIsInviteOnlyError { id: *game_id }));
+                        }
                     };
 
-                    let mut pending_users = game.pending_users;
-                    pending_users.push(*user_id);
+                    // notifications don't belong to the game, otherwise they'd get into its delivery report
+                    let mut batch = Batch::default();
+                    let username = &user.username;
+                    let game_name = &game.name;
+                    match outcome {
+                        JoinOutcome::Pending => {
+                            for manager in game.managers(Permission::Approve) {
+                                self.add_message_with_buttons_to_batch(
+                                    &mut batch,
+                                    None,
+                                    manager,
+                                    format!("{username} wants to join game {game_name}."),
+                                    vec![
+                                        (
+                                            "Approve".to_owned(),
+                                            Action::AcceptUser(*game_id, *user_id),
+                                        ),
+                                        (
+                                            "Reject".to_owned(),
+                                            Action::RejectUser(*game_id, *user_id),
+                                        ),
+                                    ],
+                                )?;
+                            }
+                        }
+                        JoinOutcome::Active if game.admin == *user_id => {}
+                        JoinOutcome::Active => self.add_messages_to_batch(
+                            &mut batch,
+                            None,
+                            vec![(
+                                game.admin,
+                                format!("{username} has joined game {game_name}."),
+                            )],
+                        )?,
+                    }
 
-                    let new_game = Game {
-                        id: game.id,
-                        name: game.name,
-                        admin: game.admin,
-                        active_users: game.active_users,
-                        pending_users,
+                    let (new_user, new_game) = match outcome {
+                        JoinOutcome::Pending => {
+                            let mut pending_games = user.pending_games;
+                            pending_games.push(*game_id);
+
+                            let mut pending_users = game.pending_users;
+                            pending_users.push(*user_id);
+
+                            (
+                                User {
+                                    pending_games,
+                                    ..user
+                                },
+                                Game {
+                                    pending_users,
+                                    ..game
+                                },
+                            )
+                        }
+                        JoinOutcome::Active => {
+                            let mut active_games = user.active_games;
+                            active_games.push(*game_id);
+
+                            let mut active_users = game.active_users;
+                            active_users.push(*user_id);
+
+                            (
+                                User {
+                                    active_games,
+                                    ..user
+                                },
+                                Game {
+                                    active_users,
+                                    ..game
+                                },
+                            )
+                        }
                     };
 
-                    let mut batch = Batch::default();
                     batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
                     batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
 
-                    self.database.apply_batch(batch)?;
+                    self.apply_game_batch(game_id, &read_game, batch)?;
 
-                    Ok(())
+                    Ok(outcome)
                 }
                 None => Err(Box::new(UserDoesNotExistError { id: *user_id })),
             },
             None => Err(Box::new(GameDoesNotExistError { id: *game_id })),
         }
     }
+    /// admin takes part in their own game without asking anyone
+    pub fn enrol_admin(&self, game_id: &GameId) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        let Some(user) = self.get_user(&game.admin) else {
+            return Err(Box::new(UserDoesNotExistError { id: game.admin }));
+        };
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+        if game.active_users.contains(&user.id) {
+            return Err(Box::new(UserIsAlreadyInGameError {
+                user_id: user.id,
+                game_id: *game_id,
+            }));
+        }
+
+        let mut pending_games = user.pending_games;
+        pending_games.retain(|id| id != game_id);
+        let mut active_games = user.active_games;
+        active_games.push(*game_id);
+
+        let mut pending_users = game.pending_users;
+        pending_users.retain(|id| *id != user.id);
+        let mut active_users = game.active_users;
+        active_users.push(user.id);
+
+        let new_user = User {
+            pending_games,
+            active_games,
+            ..user
+        };
+        let new_game = Game {
+            pending_users,
+            active_users,
+            ..game
+        };
+
+        let mut batch = Batch::default();
+        batch.insert(new_user.id.to_key().as_str(), new_user.to_ron().as_str());
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
     pub fn promote_user_from_pending_to_active(
         &self,
         user_id: &UserId,
         game_id: &GameId,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
         match self.get_game(game_id) {
             Some(game) => match self.get_user(user_id) {
                 Some(user) => {
+                    let read_game = game.to_ron();
+                    if game.is_drawn() {
+                        return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+                    }
                     if !game.pending_users.contains(user_id) {
                         return Err(Box::new(UserIsNotInPendingError {
                             user_id: *user_id,
@@ -306,31 +1430,43 @@ impl Runner {
                     active_games.push(*game_id);
 
                     let new_user = User {
-                        id: user.id,
-                        username: user.username,
-                        admin_games: user.admin_games,
                         active_games,
                         pending_games,
+                        ..user
                     };
 
                     let mut pending_users = game.pending_users;
                     pending_users.retain(|id| id != user_id);
                     let mut active_users = game.active_users;
                     active_users.push(*user_id);
+                    let mut invited_users = game.invited_users;
+                    invited_users.retain(|id| id != user_id);
+
+                    let game_name = &game.name;
+                    let mut batch = Batch::default();
+                    self.add_messages_to_batch(
+                        &mut batch,
+                        None,
+                        vec![(
+                            *user_id,
+                            format!(
+                                "You've been accepted to game {game_name}!\n\
+                                You'll get a message once presents are distributed."
+                            ),
+                        )],
+                    )?;
 
                     let new_game = Game {
-                        id: game.id,
-                        name: game.name,
-                        admin: game.admin,
                         active_users,
                         pending_users,
+                        invited_users,
+                        ..game
                     };
 
-                    let mut batch = Batch::default();
                     batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
                     batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
 
-                    self.database.apply_batch(batch)?;
+                    self.apply_game_batch(game_id, &read_game, batch)?;
 
                     Ok(())
                 }
@@ -340,45 +1476,70 @@ impl Runner {
         }
     }
     // doesn't throw an error if user is not in game or othervise
+    // user is notified unless they've left the game themselves
     pub fn remove_user_from_game(
         &self,
         user_id: &UserId,
         game_id: &GameId,
-    ) -> Result<(), Box<dyn Error>> {
+        removed_by: &UserId,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
         match self.get_game(game_id) {
             Some(game) => match self.get_user(user_id) {
                 Some(user) => {
+                    let read_game = game.to_ron();
+                    if game.is_drawn() {
+                        return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+                    }
+
+                    let mut batch = Batch::default();
+                    if removed_by != user_id
+                        && (game.pending_users.contains(user_id)
+                            || game.active_users.contains(user_id))
+                    {
+                        let game_name = &game.name;
+                        self.add_messages_to_batch(
+                            &mut batch,
+                            None,
+                            vec![(
+                                *user_id,
+                                format!("You've been removed from game {game_name}."),
+                            )],
+                        )?;
+                    }
+
                     let mut pending_games = user.pending_games;
                     pending_games.retain(|id| id != game_id);
                     let mut active_games = user.active_games;
                     active_games.retain(|id| id != game_id);
 
                     let new_user = User {
-                        id: user.id,
-                        username: user.username,
-                        admin_games: user.admin_games,
                         active_games,
                         pending_games,
+                        ..user
                     };
 
                     let mut pending_users = game.pending_users;
                     pending_users.retain(|id| id != user_id);
                     let mut active_users = game.active_users;
                     active_users.retain(|id| id != user_id);
+                    let mut invited_users = game.invited_users;
+                    invited_users.retain(|id| id != user_id);
+
+                    // a removed user can't take over the game anymore
+                    let transfer_to = game.transfer_to.filter(|id| id != user_id);
 
                     let new_game = Game {
-                        id: game.id,
-                        name: game.name,
-                        admin: game.admin,
                         active_users,
                         pending_users,
+                        invited_users,
+                        transfer_to,
+                        ..game
                     };
 
-                    let mut batch = Batch::default();
                     batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
                     batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
 
-                    self.database.apply_batch(batch)?;
+                    self.apply_game_batch(game_id, &read_game, batch)?;
 
                     Ok(())
                 }
@@ -387,4 +1548,551 @@ impl Runner {
             None => Err(Box::new(GameDoesNotExistError { id: *game_id })),
         }
     }
+    /// user is told the reason and can't ask to join again until admin allows it
+    pub fn reject_user(
+        &self,
+        user_id: &UserId,
+        game_id: &GameId,
+        reason: Option<String>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        let Some(user) = self.get_user(user_id) else {
+            return Err(Box::new(UserDoesNotExistError { id: *user_id }));
+        };
+        if !game.pending_users.contains(user_id) {
+            return Err(Box::new(UserIsNotInPendingError {
+                user_id: *user_id,
+                game_id: *game_id,
+            }));
+        }
+
+        let mut pending_games = user.pending_games;
+        pending_games.retain(|id| id != game_id);
+
+        let new_user = User {
+            pending_games,
+            ..user
+        };
+
+        let mut pending_users = game.pending_users;
+        pending_users.retain(|id| id != user_id);
+
+        let game_name = &game.name;
+        let text = match &reason {
+            Some(reason) => format!("Your request to join game {game_name} was rejected: {reason}"),
+            None => format!("Your request to join game {game_name} was rejected."),
+        };
+        let mut batch = Batch::default();
+        self.add_messages_to_batch(&mut batch, None, vec![(*user_id, text)])?;
+
+        let mut rejections = game.rejections;
+        rejections.push(Rejection {
+            user_id: *user_id,
+            reason,
+        });
+
+        let new_game = Game {
+            pending_users,
+            rejections,
+            ..game
+        };
+
+        batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn allow_rejoin(
+        &self,
+        user_id: &UserId,
+        game_id: &GameId,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+
+        let mut rejections = game.rejections;
+        rejections.retain(|rejection| rejection.user_id != *user_id);
+
+        let new_game = Game { rejections, ..game };
+        let mut batch = Batch::default();
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// makes user a co-admin of the game or replaces their permissions
+    pub fn grant_permissions(
+        &self,
+        game_id: &GameId,
+        user_id: &UserId,
+        permissions: Vec<Permission>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        let Some(user) = self.get_user(user_id) else {
+            return Err(Box::new(UserDoesNotExistError { id: *user_id }));
+        };
+        if game.admin == *user_id {
+            return Err(Box::new(UserIsAdminError {
+                user_id: *user_id,
+                game_id: *game_id,
+            }));
+        }
+
+        let game_name = &game.name;
+        let described: Vec<String> = permissions.iter().map(ToString::to_string).collect();
+        let mut batch = Batch::default();
+        self.add_messages_to_batch(
+            &mut batch,
+            None,
+            vec![(
+                *user_id,
+                format!(
+                    "You can now help to manage game {game_name}: {}.\n\
+                    Use /help to see what you can do.",
+                    described.join(", ")
+                ),
+            )],
+        )?;
+
+        let mut co_admins = game.co_admins;
+        co_admins.retain(|co_admin| co_admin.user_id != *user_id);
+        co_admins.push(CoAdmin {
+            user_id: *user_id,
+            permissions,
+        });
+
+        let mut co_admin_games = user.co_admin_games;
+        if !co_admin_games.contains(game_id) {
+            co_admin_games.push(*game_id);
+        }
+
+        let new_game = Game { co_admins, ..game };
+        let new_user = User {
+            co_admin_games,
+            ..user
+        };
+
+        batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn revoke_permissions(
+        &self,
+        game_id: &GameId,
+        user_id: &UserId,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        let Some(user) = self.get_user(user_id) else {
+            return Err(Box::new(UserDoesNotExistError { id: *user_id }));
+        };
+
+        let mut co_admins = game.co_admins;
+        co_admins.retain(|co_admin| co_admin.user_id != *user_id);
+
+        let mut co_admin_games = user.co_admin_games;
+        co_admin_games.retain(|id| id != game_id);
+
+        let new_game = Game { co_admins, ..game };
+        let new_user = User {
+            co_admin_games,
+            ..user
+        };
+
+        let mut batch = Batch::default();
+        batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// new admin has to accept the game before it's transferred
+    pub fn offer_transfer(
+        &self,
+        game_id: &GameId,
+        user_id: &UserId,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.admin == *user_id {
+            return Err(Box::new(UserIsAdminError {
+                user_id: *user_id,
+                game_id: *game_id,
+            }));
+        }
+        if !game.active_users.contains(user_id) {
+            return Err(Box::new(UserIsNotInGameError {
+                user_id: *user_id,
+                game_id: *game_id,
+            }));
+        }
+
+        let game_name = &game.name;
+        let mut batch = Batch::default();
+        self.add_message_with_buttons_to_batch(
+            &mut batch,
+            None,
+            *user_id,
+            format!("The admin of game {game_name} wants you to become its new admin."),
+            vec![
+                ("Accept".to_owned(), Action::AcceptTransfer(*game_id)),
+                ("Decline".to_owned(), Action::DeclineTransfer(*game_id)),
+            ],
+        )?;
+
+        let new_game = Game {
+            transfer_to: Some(*user_id),
+            ..game
+        };
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// the game and both admins are updated together, so the game never has zero or two admins
+    pub fn accept_transfer(
+        &self,
+        game_id: &GameId,
+        user_id: &UserId,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.transfer_to != Some(*user_id) {
+            return Err(Box::new(GameIsNotOfferedError {
+                user_id: *user_id,
+                game_id: *game_id,
+            }));
+        }
+        if !game.active_users.contains(user_id) {
+            return Err(Box::new(UserIsNotInGameError {
+                user_id: *user_id,
+                game_id: *game_id,
+            }));
+        }
+        let Some(new_admin) = self.get_user(user_id) else {
+            return Err(Box::new(UserDoesNotExistError { id: *user_id }));
+        };
+        let Some(old_admin) = self.get_user(&game.admin) else {
+            return Err(Box::new(UserDoesNotExistError { id: game.admin }));
+        };
+
+        let mut admin_games = old_admin.admin_games;
+        admin_games.retain(|id| id != game_id);
+        let old_admin = User {
+            admin_games,
+            ..old_admin
+        };
+
+        let mut admin_games = new_admin.admin_games;
+        admin_games.push(*game_id);
+        // admin is allowed everything anyway
+        let mut co_admin_games = new_admin.co_admin_games;
+        co_admin_games.retain(|id| id != game_id);
+        let new_admin = User {
+            admin_games,
+            co_admin_games,
+            ..new_admin
+        };
+
+        let mut co_admins = game.co_admins;
+        co_admins.retain(|co_admin| co_admin.user_id != *user_id);
+
+        let game_name = &game.name;
+        let mut batch = Batch::default();
+        self.add_messages_to_batch(
+            &mut batch,
+            None,
+            vec![(
+                old_admin.id,
+                format!(
+                    "{} is the new admin of game {game_name}.",
+                    new_admin.username
+                ),
+            )],
+        )?;
+
+        let new_game = Game {
+            admin: *user_id,
+            co_admins,
+            transfer_to: None,
+            ..game
+        };
+
+        batch.insert(old_admin.id.to_key().as_str(), old_admin.to_ron().as_str());
+        batch.insert(user_id.to_key().as_str(), new_admin.to_ron().as_str());
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    pub fn decline_transfer(
+        &self,
+        game_id: &GameId,
+        user_id: &UserId,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        if game.transfer_to != Some(*user_id) {
+            return Err(Box::new(GameIsNotOfferedError {
+                user_id: *user_id,
+                game_id: *game_id,
+            }));
+        }
+        let Some(user) = self.get_user(user_id) else {
+            return Err(Box::new(UserDoesNotExistError { id: *user_id }));
+        };
+
+        let game_name = &game.name;
+        let mut batch = Batch::default();
+        self.add_messages_to_batch(
+            &mut batch,
+            None,
+            vec![(
+                game.admin,
+                format!(
+                    "{} has declined to become the admin of game {game_name}.",
+                    user.username
+                ),
+            )],
+        )?;
+
+        let new_game = Game {
+            transfer_to: None,
+            ..game
+        };
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// new game gets settings and exclusion rules of the old one,
+    /// previous participants are added to pending and asked to confirm it
+    pub fn clone_game(
+        &self,
+        game_id: &GameId,
+        name: String,
+        invite_participants: bool,
+    ) -> Result<GameId, Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let Some(admin) = self.get_user(&game.admin) else {
+            return Err(Box::new(UserDoesNotExistError { id: game.admin }));
+        };
+
+        let new_game_id = self.new_game(
+            game.admin,
+            name,
+            game.draw_mode,
+            game.budget.clone(),
+            game.active_users.contains(&game.admin),
+        )?;
+        let new_game = self.get_game(&new_game_id).unwrap();
+        let read_game = new_game.to_ron();
+
+        let invited_users: Vec<UserId> = match invite_participants {
+            true => game
+                .active_users
+                .iter()
+                .filter(|user_id| **user_id != game.admin)
+                .copied()
+                .collect(),
+            false => vec![],
+        };
+
+        // assignments of the old game are used to avoid repeats
+        let previous_game = match game.is_drawn() {
+            true => Some(*game_id),
+            false => game.previous_game,
+        };
+
+        let mut batch = Batch::default();
+        for user_id in &invited_users {
+            let Some(user) = self.get_user(user_id) else {
+                return Err(Box::new(UserDoesNotExistError { id: *user_id }));
+            };
+
+            self.add_message_with_buttons_to_batch(
+                &mut batch,
+                None,
+                *user_id,
+                format!(
+                    "{} invites you to game {} since you took part in {}.",
+                    admin.username, new_game.name, game.name
+                ),
+                vec![
+                    ("I'm in".to_owned(), Action::ConfirmInvitation(new_game_id)),
+                    (
+                        "Not this time".to_owned(),
+                        Action::DeclineInvitation(new_game_id),
+                    ),
+                ],
+            )?;
+
+            let mut pending_games = user.pending_games;
+            pending_games.push(new_game_id);
+            let new_user = User {
+                pending_games,
+                ..user
+            };
+            batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
+        }
+
+        let new_game = Game {
+            pending_users: invited_users.clone(),
+            invited_users,
+            exclusions: game.exclusions,
+            join_policy: game.join_policy,
+            reminders: game
+                .reminders
+                .into_iter()
+                .map(|reminder| Reminder {
+                    sent_for: None,
+                    ..reminder
+                })
+                .collect(),
+            previous_game,
+            ..new_game
+        };
+        batch.insert(new_game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(&new_game_id, &read_game, batch)?;
+
+        Ok(new_game_id)
+    }
+    /// invited user confirms they take part, admin doesn't have to accept them
+    pub fn accept_invitation(
+        &self,
+        user_id: &UserId,
+        game_id: &GameId,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let read_game = game.to_ron();
+        let Some(user) = self.get_user(user_id) else {
+            return Err(Box::new(UserDoesNotExistError { id: *user_id }));
+        };
+        if game.is_drawn() {
+            return Err(Box::new(GameIsAlreadyDrawnError { id: *game_id }));
+        }
+        if !game.invited_users.contains(user_id) || !game.pending_users.contains(user_id) {
+            return Err(Box::new(UserIsNotInvitedError {
+                user_id: *user_id,
+                game_id: *game_id,
+            }));
+        }
+
+        let mut pending_games = user.pending_games;
+        pending_games.retain(|id| id != game_id);
+        let mut active_games = user.active_games;
+        active_games.push(*game_id);
+
+        let mut pending_users = game.pending_users;
+        pending_users.retain(|id| id != user_id);
+        let mut active_users = game.active_users;
+        active_users.push(*user_id);
+        let mut invited_users = game.invited_users;
+        invited_users.retain(|id| id != user_id);
+
+        let game_name = &game.name;
+        let mut batch = Batch::default();
+        self.add_messages_to_batch(
+            &mut batch,
+            None,
+            vec![(
+                game.admin,
+                format!("{} has joined game {game_name}.", user.username),
+            )],
+        )?;
+
+        let new_user = User {
+            pending_games,
+            active_games,
+            ..user
+        };
+        let new_game = Game {
+            pending_users,
+            active_users,
+            invited_users,
+            ..game
+        };
+
+        batch.insert(user_id.to_key().as_str(), new_user.to_ron().as_str());
+        batch.insert(game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(game_id, &read_game, batch)?;
+
+        Ok(())
+    }
+    /// clones a drawn yearly game, the new edition is scheduled for the next year
+    /// and remembers the old one, so nobody gets the same recipient again
+    fn spawn_next_edition(&self, game_id: &GameId) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let Some(game) = self.get_game(game_id) else {
+            return Err(Box::new(GameDoesNotExistError { id: *game_id }));
+        };
+        let Some(recurrence) = game.recurrence else {
+            return Ok(());
+        };
+
+        // the edition after the drawn one, even if it was drawn before its own date
+        let mut recurrence = recurrence.next();
+        while recurrence.draw_at().timestamp <= now() {
+            recurrence = recurrence.next();
+        }
+        let draw_at = recurrence.draw_at();
+
+        let new_game_id = self.clone_game(game_id, next_edition_name(&game.name), true)?;
+        let new_game = self.get_game(&new_game_id).unwrap();
+        let read_game = new_game.to_ron();
+        let new_game = Game {
+            draw_at: Some(draw_at),
+            recurrence: Some(recurrence),
+            ..new_game
+        };
+
+        let mut batch = Batch::default();
+        self.add_messages_to_batch(
+            &mut batch,
+            None,
+            vec![(
+                game.admin,
+                format!(
+                    "Game {} is waiting for the next year, it will be drawn at {draw_at}.\n\
+                    Everyone who took part in {} was invited to it.",
+                    new_game.name, game.name
+                ),
+            )],
+        )?;
+        batch.insert(new_game_id.to_key().as_str(), new_game.to_ron().as_str());
+
+        self.apply_game_batch(&new_game_id, &read_game, batch)?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,63 @@
+use crate::{errors::*, runner::Runner, utils::*};
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// closes registrations, runs draws and sends reminders on schedule forever,
+/// messages are sent by the outbox
+pub async fn run(runner: Runner) {
+    loop {
+        close_due_registrations(&runner);
+        run_due_draws(&runner).await;
+        if let Err(error) = runner.send_due_reminders(now()) {
+            log::error!("Unable to send reminders: {error}");
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+fn close_due_registrations(runner: &Runner) {
+    for game in runner.due_registration_closings(now()) {
+        if let Err(error) = runner.lock_game(&game.id, None) {
+            log::error!("Unable to close registration to game {}: {error}", game.id);
+        }
+    }
+}
+
+async fn run_due_draws(runner: &Runner) {
+    for game in runner.due_draws(now()) {
+        // the draw may take a while, so it doesn't block the bot
+        let draw_runner = runner.clone();
+        let result = tokio::task::spawn_blocking(move || draw_runner.run_game(game.id))
+            .await
+            .unwrap_or_else(|error| Err(error.into()));
+        let Err(error) = result else {
+            continue;
+        };
+        // the game was drawn or changed by its admin meanwhile, it's checked again on the next run
+        if error.is::<GameIsAlreadyDrawnError>() || error.is::<GameWasChangedError>() {
+            continue;
+        }
+
+        // the schedule is dropped so the same draw isn't retried every time
+        let game_name = game.name;
+        let result = runner.set_draw_time(&game.id, None).and_then(|_| {
+            runner.enqueue_messages(
+                None,
+                vec![(
+                    game.admin,
+                    format!(
+                        "Unable to run scheduled draw of game {game_name}.\n{error}\n\n\
+                        Please fix it and use /run or /schedule again."
+                    ),
+                )],
+            )
+        });
+        if let Err(error) = result {
+            log::error!(
+                "Unable to cancel scheduled draw of game {}: {error}",
+                game.id
+            );
+        }
+    }
+}
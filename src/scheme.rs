@@ -1,14 +1,18 @@
+use std::error::Error;
 use teloxide::{
     dispatching::{dialogue, dialogue::InMemStorage, UpdateFilterExt, UpdateHandler},
     dptree,
-    payloads::SendMessageSetters,
+    payloads::{SendMessageSetters, SendPhotoSetters},
     prelude::{ChatId, Dialogue, Requester},
-    types::{Message, ParseMode, Update},
-    utils::command::BotCommands,
+    types::{
+        CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, Message, ParseMode,
+        Update,
+    },
+    utils::{command::BotCommands, markdown},
     Bot,
 };
 
-use crate::{runner::*, utils::*};
+use crate::{errors::*, runner::*, utils::*};
 
 #[derive(Clone, Default)]
 pub enum State {
@@ -35,17 +39,84 @@ pub enum State {
     Accept {
         state: AcceptState,
     },
+    Reject {
+        state: RejectState,
+    },
     Remove {
         state: RemoveState,
     },
     Info {
         state: InfoState,
     },
+    Exclude {
+        state: ExcludeState,
+    },
+    Allow {
+        state: AllowState,
+    },
+    Mode {
+        state: ModeState,
+    },
+    Link {
+        state: LinkState,
+    },
+    Archive {
+        state: ArchiveState,
+    },
+    Wishlist {
+        state: WishlistState,
+    },
+    Relay {
+        state: RelayState,
+    },
+    Budget {
+        state: BudgetState,
+    },
+    Schedule {
+        state: ScheduleState,
+    },
+    Lock {
+        state: LockState,
+    },
+    Unlock {
+        state: UnlockState,
+    },
+    Invite {
+        state: InviteState,
+    },
+    Policy {
+        state: PolicyState,
+    },
+    Grant {
+        state: GrantState,
+    },
+    Revoke {
+        state: RevokeState,
+    },
+    Transfer {
+        state: TransferState,
+    },
+    Delete {
+        state: DeleteState,
+    },
+    Clone {
+        state: CloneState,
+    },
+    Repeat {
+        state: RepeatState,
+    },
+    Party {
+        state: PartyState,
+    },
+    Remind {
+        state: RemindState,
+    },
 }
 
 #[derive(Clone)]
 pub enum RegisterState {
-    GetName,
+    /// `invite` is the game user followed an invite link to
+    GetName { invite: Option<GameId> },
 }
 
 #[derive(Clone)]
@@ -56,6 +127,18 @@ pub enum UsernameState {
 #[derive(Clone)]
 pub enum CreateState {
     GetName,
+    ChooseDrawMode {
+        game_name: String,
+    },
+    GetBudget {
+        game_name: String,
+        draw_mode: DrawMode,
+    },
+    ChooseParticipation {
+        game_name: String,
+        draw_mode: DrawMode,
+        budget: Option<Budget>,
+    },
 }
 
 #[derive(Clone)]
@@ -67,6 +150,7 @@ pub enum RunState {
 #[derive(Clone)]
 pub enum JoinState {
     GetId,
+    GetPassword { game_id: GameId },
 }
 
 #[derive(Clone)]
@@ -80,6 +164,13 @@ pub enum AcceptState {
     GetUserId { game_id: GameId },
 }
 
+#[derive(Clone)]
+pub enum RejectState {
+    GetGameId,
+    ChooseUser { game_id: GameId },
+    GetReason { game_id: GameId, user_id: UserId },
+}
+
 #[derive(Clone)]
 pub enum RemoveState {
     GetGameId,
@@ -91,6 +182,203 @@ pub enum InfoState {
     GetId,
 }
 
+#[derive(Clone)]
+pub enum ExcludeState {
+    GetGameId,
+    GetPresenterId {
+        game_id: GameId,
+    },
+    GetRecipientId {
+        game_id: GameId,
+        presenter: UserId,
+    },
+    ChooseDirection {
+        game_id: GameId,
+        presenter: UserId,
+        recipient: UserId,
+    },
+}
+
+#[derive(Clone)]
+pub enum ModeState {
+    GetGameId,
+    GetDrawMode { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum LinkState {
+    GetGameId,
+    GetPreviousGameId { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum ArchiveState {
+    GetId,
+}
+
+#[derive(Clone)]
+pub enum WishlistState {
+    GetGameId,
+    Wishes {
+        game_id: GameId,
+    },
+    Sizes {
+        game_id: GameId,
+        wishes: String,
+    },
+    Dislikes {
+        game_id: GameId,
+        wishes: String,
+        sizes: String,
+    },
+}
+
+#[derive(Clone, Copy)]
+pub enum RelayDirection {
+    ToRecipient,
+    ToSanta,
+}
+
+#[derive(Clone)]
+pub enum RelayState {
+    GetGameId {
+        direction: RelayDirection,
+    },
+    Chat {
+        game_id: GameId,
+        direction: RelayDirection,
+    },
+}
+
+#[derive(Clone)]
+pub enum BudgetState {
+    GetGameId,
+    GetBudget { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum ScheduleState {
+    GetGameId,
+    GetDateTime { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum LockState {
+    GetGameId,
+    GetDateTime { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum UnlockState {
+    GetGameId,
+}
+
+#[derive(Clone)]
+pub enum InviteState {
+    GetGameId,
+}
+
+#[derive(Clone)]
+pub enum PolicyState {
+    GetGameId,
+    ChoosePolicy { game_id: GameId },
+    GetPassword { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum GrantState {
+    GetGameId,
+    ChooseUser { game_id: GameId },
+    GetPermissions { game_id: GameId, user_id: UserId },
+}
+
+#[derive(Clone)]
+pub enum RevokeState {
+    GetGameId,
+    ChooseUser { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum CloneState {
+    GetGameId,
+    GetName { game_id: GameId },
+    ChooseInvite { game_id: GameId, game_name: String },
+}
+
+#[derive(Clone)]
+pub enum RepeatState {
+    GetGameId,
+    GetDateTime { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum DeleteState {
+    GetGameId,
+    Confirm { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum TransferState {
+    GetGameId,
+    ChooseUser { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum PartyState {
+    GetGameId,
+    GetDateTime { game_id: GameId },
+}
+
+#[derive(Clone)]
+pub enum RemindState {
+    GetGameId,
+    ChooseEvent {
+        game_id: GameId,
+    },
+    GetHours {
+        game_id: GameId,
+        event: ReminderEvent,
+    },
+}
+
+#[derive(Clone)]
+pub enum AllowState {
+    GetGameId,
+    GetNumber { game_id: GameId },
+}
+
+const DRAW_MODE_QUESTION: &str = "How should presents be distributed?\n\n\
+    `pairs` \\- anyone can prepare a present for anyone else\n\
+    `chain` \\- everyone forms one big chain, so there are no closed swaps\n\n\
+    Please answer `pairs` or `chain`";
+
+const JOIN_POLICY_QUESTION: &str = "Who can join this game?\n\n\
+    `manual` \\- everyone waits until you /accept them\n\
+    `auto` \\- everyone joins right away\n\
+    `password` \\- those who know the password join right away\n\
+    `invite` \\- only those who have an /invite link can join, they join right away\n\n\
+    Please answer `manual`, `auto`, `password` or `invite`";
+
+const PERMISSIONS_QUESTION: &str = "What should they be allowed to do?\n\n\
+    `approve` \\- accept and reject join requests\n\
+    `remove` \\- remove participants\n\
+    `settings` \\- change rules and settings of the game\n\
+    `run` \\- run the game or schedule it\n\n\
+    Please answer with a comma separated list like `approve, remove` or `all`";
+
+const REMINDER_EVENT_QUESTION: &str = "What should participants be reminded of?\n\n\
+    `registration` \\- registration to the game is about to close\n\
+    `draw` \\- presents are about to be distributed\n\
+    `party` \\- the gift exchange party is coming\n\n\
+    Please answer `registration`, `draw` or `party`";
+
+const BUDGET_QUESTION: &str = "How much should presents cost?\n\
+    Please answer like 10-50 EUR or 50 EUR.\n\
+    Send - if there's no budget.";
+
+const PARTICIPATION_QUESTION: &str = "Are you going to take part in this game yourself?\n\
+    Please answer yes or no.";
+
 type MyDialogue = Dialogue<State, InMemStorage<State>>;
 pub type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
 
@@ -101,7 +389,7 @@ pub type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
 )]
 enum Command {
     #[command(description = "please use this command to register if you haven't!")]
-    Start,
+    Start(String),
     #[command(description = "display this text.")]
     Help,
     #[command(description = "changes your username.")]
@@ -118,10 +406,60 @@ enum Command {
     List,
     #[command(description = "accept someone to one of your games.")]
     Accept,
+    #[command(description = "reject someone who wants to join one of your games.")]
+    Reject,
     #[command(description = "remove someone from one of your games.")]
     Remove,
     #[command(description = "get info about one of your games.")]
     Info,
+    #[command(description = "forbid someone to prepare a present for someone else.")]
+    Exclude,
+    #[command(description = "remove an exclusion rule from one of your games.")]
+    Allow,
+    #[command(description = "change how presents are distributed in one of your games.")]
+    Mode,
+    #[command(
+        description = "link one of your games to its previous edition to avoid repeating pairs."
+    )]
+    Link,
+    #[command(description = "hide one of your past games from the list.")]
+    Archive,
+    #[command(description = "remind who you're preparing presents for.")]
+    MySanta,
+    #[command(description = "tell your secret santa what you'd like to get.")]
+    Wishlist,
+    #[command(description = "anonymously message the one you're preparing a present for.")]
+    ToRecipient,
+    #[command(description = "anonymously message your secret santa.")]
+    ToSanta,
+    #[command(description = "change how much presents should cost in one of your games.")]
+    Budget,
+    #[command(description = "run one of your games automatically at given time.")]
+    Schedule,
+    #[command(description = "close registration to one of your games.")]
+    Lock,
+    #[command(description = "open registration to one of your games.")]
+    Unlock,
+    #[command(description = "get a link that lets people join one of your games.")]
+    Invite,
+    #[command(description = "choose who can join one of your games.")]
+    Policy,
+    #[command(description = "let someone help you to manage one of your games.")]
+    Grant,
+    #[command(description = "stop someone from helping you to manage one of your games.")]
+    Revoke,
+    #[command(description = "make another participant the admin of one of your games.")]
+    Transfer,
+    #[command(description = "cancel one of your games before it's drawn.")]
+    Delete,
+    #[command(description = "start one of your games again, e.g. for the next year.")]
+    Clone,
+    #[command(description = "make one of your games repeat every year.")]
+    Repeat,
+    #[command(description = "set when the gift exchange party of one of your games is.")]
+    Party,
+    #[command(description = "remind participants of one of your games about upcoming events.")]
+    Remind,
     #[command(description = "cancel operation.")]
     Cancel,
 }
@@ -133,7 +471,7 @@ pub fn schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'stat
         .branch(
             // catches all cases where there's no already started dialog
             case![State::None]
-                .branch(case![Command::Start].endpoint(start_cmd))
+                .branch(case![Command::Start(payload)].endpoint(start_cmd))
                 .branch(case![Command::Help].endpoint(help_cmd))
                 .branch(case![Command::Username].endpoint(username_cmd))
                 .branch(case![Command::Create].endpoint(create_cmd))
@@ -141,8 +479,32 @@ pub fn schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'stat
                 .branch(case![Command::Join].endpoint(join_cmd))
                 .branch(case![Command::Leave].endpoint(leave_cmd))
                 .branch(case![Command::Accept].endpoint(accept_cmd))
+                .branch(case![Command::Reject].endpoint(reject_cmd))
                 .branch(case![Command::Remove].endpoint(remove_cmd))
                 .branch(case![Command::Info].endpoint(info_cmd))
+                .branch(case![Command::Exclude].endpoint(exclude_cmd))
+                .branch(case![Command::Allow].endpoint(allow_cmd))
+                .branch(case![Command::Mode].endpoint(mode_cmd))
+                .branch(case![Command::Link].endpoint(link_cmd))
+                .branch(case![Command::Archive].endpoint(archive_cmd))
+                .branch(case![Command::MySanta].endpoint(my_santa_cmd))
+                .branch(case![Command::Wishlist].endpoint(wishlist_cmd))
+                .branch(case![Command::ToRecipient].endpoint(to_recipient_cmd))
+                .branch(case![Command::ToSanta].endpoint(to_santa_cmd))
+                .branch(case![Command::Budget].endpoint(budget_cmd))
+                .branch(case![Command::Schedule].endpoint(schedule_cmd))
+                .branch(case![Command::Lock].endpoint(lock_cmd))
+                .branch(case![Command::Unlock].endpoint(unlock_cmd))
+                .branch(case![Command::Invite].endpoint(invite_cmd))
+                .branch(case![Command::Policy].endpoint(policy_cmd))
+                .branch(case![Command::Grant].endpoint(grant_cmd))
+                .branch(case![Command::Revoke].endpoint(revoke_cmd))
+                .branch(case![Command::Transfer].endpoint(transfer_cmd))
+                .branch(case![Command::Delete].endpoint(delete_cmd))
+                .branch(case![Command::Clone].endpoint(clone_cmd))
+                .branch(case![Command::Repeat].endpoint(repeat_cmd))
+                .branch(case![Command::Party].endpoint(party_cmd))
+                .branch(case![Command::Remind].endpoint(remind_cmd))
                 .branch(case![Command::List].endpoint(list_cmd)),
         )
         // catch case if user wants to leave
@@ -157,31 +519,86 @@ pub fn schema() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'stat
         .branch(case![State::Join { state }].endpoint(join))
         .branch(case![State::Leave { state }].endpoint(leave))
         .branch(case![State::Accept { state }].endpoint(accept))
+        .branch(case![State::Reject { state }].endpoint(reject))
         .branch(case![State::Remove { state }].endpoint(remove))
         .branch(case![State::Info { state }].endpoint(info))
+        .branch(case![State::Exclude { state }].endpoint(exclude))
+        .branch(case![State::Allow { state }].endpoint(allow))
+        .branch(case![State::Mode { state }].endpoint(mode))
+        .branch(case![State::Link { state }].endpoint(link))
+        .branch(case![State::Archive { state }].endpoint(archive))
+        .branch(case![State::Wishlist { state }].endpoint(wishlist))
+        .branch(case![State::Relay { state }].endpoint(relay))
+        .branch(case![State::Budget { state }].endpoint(budget))
+        .branch(case![State::Schedule { state }].endpoint(schedule))
+        .branch(case![State::Lock { state }].endpoint(lock))
+        .branch(case![State::Unlock { state }].endpoint(unlock))
+        .branch(case![State::Invite { state }].endpoint(invite))
+        .branch(case![State::Policy { state }].endpoint(policy))
+        .branch(case![State::Grant { state }].endpoint(grant))
+        .branch(case![State::Revoke { state }].endpoint(revoke))
+        .branch(case![State::Transfer { state }].endpoint(transfer))
+        .branch(case![State::Delete { state }].endpoint(delete))
+        .branch(case![State::Clone { state }].endpoint(clone))
+        .branch(case![State::Repeat { state }].endpoint(repeat))
+        .branch(case![State::Party { state }].endpoint(party))
+        .branch(case![State::Remind { state }].endpoint(remind))
         .branch(dptree::endpoint(invalid_state));
 
-    dialogue::enter::<Update, InMemStorage<State>, State, _>().branch(message_handler)
+    let callback_handler = Update::filter_callback_query().endpoint(callback);
+
+    dialogue::enter::<Update, InMemStorage<State>, State, _>()
+        .branch(message_handler)
+        .branch(callback_handler)
 }
 
-async fn start_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
-    match runner.get_user(&UserId::from(msg.chat.id)) {
-        Some(_) => {
-            bot.send_message(
-                msg.chat.id,
-                "It looks like you're already registered.\n\
-                You can change your username using /username\n\
-                Use /help to get more info.",
-            )
+async fn start_cmd(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    payload: String,
+    runner: Runner,
+) -> HandlerResult {
+    // invite links look like t.me/<bot>?start=join_<token>
+    let invite = payload
+        .trim()
+        .strip_prefix("join_")
+        .and_then(|token| runner.find_game_by_invite(&InviteToken(token.to_owned())));
+    if !payload.trim().is_empty() && invite.is_none() {
+        bot.send_message(msg.chat.id, "It looks like this invite link is broken.")
             .await?;
-        }
+    }
+
+    match runner.get_user(&UserId::from(msg.chat.id)) {
+        Some(_) => match invite {
+            Some(game_id) => {
+                join_game(
+                    &bot,
+                    &dialogue,
+                    msg.chat.id,
+                    &runner,
+                    &game_id,
+                    JoinRequest::Invite,
+                )
+                .await?
+            }
+            None => {
+                bot.send_message(
+                    msg.chat.id,
+                    "It looks like you're already registered.\n\
+                    You can change your username using /username\n\
+                    Use /help to get more info.",
+                )
+                .await?;
+            }
+        },
         None => {
             bot.send_message(msg.chat.id, "Let's start! How should I call you?")
                 .await?;
 
             dialogue
                 .update(State::Register {
-                    state: RegisterState::GetName,
+                    state: RegisterState::GetName { invite },
                 })
                 .await?;
         }
@@ -227,46 +644,33 @@ async fn create_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResu
 }
 
 async fn run_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
-    let mut message = String::from(
-        "Please enter id of the game you want to run\n\
-        You can /cancel\n\n\
-        Here are available options:\n",
-    );
+    let games = managed_games(&runner, &UserId::from(msg.chat.id), Permission::Run)
+        .into_iter()
+        .filter(|game| !game.is_drawn())
+        .collect();
 
-    match runner.get_user(&UserId::from(msg.chat.id)) {
-        Some(user) => {
-            user.admin_games
-                .iter()
-                .for_each(|elem| match runner.get_game(elem) {
-                    Some(game) => {
-                        message.push_str(format!("{game}").as_str());
-                    }
-                    None => {
-                        todo!()
-                    }
-                });
-        }
-        None => {
-            todo!()
-        }
+    if offer_games(
+        &bot,
+        msg.chat.id,
+        games,
+        Action::Run,
+        "Please choose the game you want to run.",
+    )
+    .await?
+    {
+        dialogue
+            .update(State::Run {
+                state: RunState::GetId,
+            })
+            .await?;
     }
-
-    bot.send_message(msg.chat.id, message)
-        .parse_mode(ParseMode::MarkdownV2)
-        .await?;
-
-    dialogue
-        .update(State::Run {
-            state: RunState::GetId,
-        })
-        .await?;
     Ok(())
 }
 
 async fn join_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
     bot.send_message(
         msg.chat.id,
-        "Please enter id of the game you want to join.\n\
+        "Please enter code of the game you want to join.\n\
         You can /cancel",
     )
     .await?;
@@ -278,49 +682,102 @@ async fn join_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult
     Ok(())
 }
 
-async fn leave_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
-    bot.send_message(
+async fn leave_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = user_games(&runner, &UserId::from(msg.chat.id), |user| {
+        [user.active_games, user.pending_games].concat()
+    })
+    .into_iter()
+    .filter(|game| !game.is_drawn())
+    .collect();
+
+    if offer_games(
+        &bot,
         msg.chat.id,
-        "Please enter id of the game you want to leave.\n\
-            You can /cancel\n\
+        games,
+        Action::Leave,
+        "Please choose the game you want to leave.\n\
             You will be able to rejoin this game.",
     )
-    .await?;
-    dialogue
-        .update(State::Leave {
-            state: LeaveState::GetId,
-        })
-        .await?;
+    .await?
+    {
+        dialogue
+            .update(State::Leave {
+                state: LeaveState::GetId,
+            })
+            .await?;
+    }
     Ok(())
 }
 
-async fn accept_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
-    bot.send_message(
+async fn accept_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = managed_games(&runner, &UserId::from(msg.chat.id), Permission::Approve)
+        .into_iter()
+        .filter(|game| !game.is_drawn() && !game.pending_users.is_empty())
+        .collect();
+
+    if offer_games(
+        &bot,
         msg.chat.id,
-        "Please enter id of the game you want to manage.\n\
-            You can /cancel",
+        games,
+        Action::Accept,
+        "Please choose the game you want to manage.",
     )
-    .await?;
-    dialogue
-        .update(State::Accept {
-            state: AcceptState::GetGameId,
-        })
-        .await?;
+    .await?
+    {
+        dialogue
+            .update(State::Accept {
+                state: AcceptState::GetGameId,
+            })
+            .await?;
+    }
     Ok(())
 }
 
-async fn remove_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
-    bot.send_message(
+async fn reject_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = managed_games(&runner, &UserId::from(msg.chat.id), Permission::Approve)
+        .into_iter()
+        .filter(|game| !game.is_drawn() && !game.pending_users.is_empty())
+        .collect();
+
+    if offer_games(
+        &bot,
         msg.chat.id,
-        "Please enter id of the game you want to manage.\n\
-            You can /cancel",
+        games,
+        Action::Reject,
+        "Please choose the game you want to manage.",
     )
-    .await?;
-    dialogue
-        .update(State::Remove {
-            state: RemoveState::GetGameId,
-        })
-        .await?;
+    .await?
+    {
+        dialogue
+            .update(State::Reject {
+                state: RejectState::GetGameId,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn remove_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = managed_games(&runner, &UserId::from(msg.chat.id), Permission::Remove)
+        .into_iter()
+        .filter(|game| !game.is_drawn())
+        .collect();
+
+    if offer_games(
+        &bot,
+        msg.chat.id,
+        games,
+        Action::Remove,
+        "Please choose the game you want to manage.",
+    )
+    .await?
+    {
+        dialogue
+            .update(State::Remove {
+                state: RemoveState::GetGameId,
+            })
+            .await?;
+    }
     Ok(())
 }
 
@@ -333,6 +790,28 @@ async fn list_cmd(bot: Bot, _dialogue: MyDialogue, msg: Message, runner: Runner)
                 format!("Hi, {username} Here's list of all your games:"),
             )
             .await?;
+
+            let is_drawn = |id: &GameId| runner.get_game(id).is_some_and(|game| game.is_drawn());
+            let (past_active_games, active_games): (Vec<GameId>, Vec<GameId>) =
+                user.active_games.into_iter().partition(is_drawn);
+            let (past_admin_games, admin_games): (Vec<GameId>, Vec<GameId>) =
+                [user.admin_games, user.co_admin_games]
+                    .concat()
+                    .into_iter()
+                    .partition(is_drawn);
+
+            // archived games are hidden, they're still available with /info
+            let mut past_games: Vec<GameId> = Vec::new();
+            for game_id in past_active_games.into_iter().chain(past_admin_games) {
+                let is_archived = runner
+                    .get_game(&game_id)
+                    .is_some_and(|game| game.status == GameStatus::Archived);
+                if !is_archived && !past_games.contains(&game_id) {
+                    past_games.push(game_id);
+                }
+            }
+
+            // games can be deleted while users still refer to them, they're just not listed then
             match user.pending_games.len() {
                 0 => {
                     bot.send_message(msg.chat.id, "There was no pending games found.")
@@ -340,60 +819,58 @@ async fn list_cmd(bot: Bot, _dialogue: MyDialogue, msg: Message, runner: Runner)
                 }
                 _ => {
                     let mut message: String = String::from("Here are your pending games:\n\n");
-                    for pending_game in user.pending_games {
-                        match runner.get_game(&pending_game) {
-                            Some(game) => {
-                                message.push_str(format!("{}\n", game).as_str());
-                            }
-                            None => {
-                                todo!()
-                            }
-                        }
-                    }
+                    user.pending_games
+                        .iter()
+                        .filter_map(|game_id| runner.get_game(game_id))
+                        .for_each(|game| message.push_str(format!("{}\n", game).as_str()));
                     bot.send_message(msg.chat.id, message.as_str())
                         .parse_mode(ParseMode::MarkdownV2)
                         .await?;
                 }
             }
-            match user.active_games.len() {
+            match active_games.len() {
                 0 => {
                     bot.send_message(msg.chat.id, "There was no user active found.")
                         .await?;
                 }
                 _ => {
                     let mut message: String = String::from("Here are your active games:\n\n");
-                    for active_game in user.active_games {
-                        match runner.get_game(&active_game) {
-                            Some(game) => {
-                                message.push_str(format!("{}\n", game).as_str());
-                            }
-                            None => {
-                                todo!()
-                            }
-                        }
-                    }
+                    active_games
+                        .iter()
+                        .filter_map(|game_id| runner.get_game(game_id))
+                        .for_each(|game| message.push_str(format!("{}\n", game).as_str()));
                     bot.send_message(msg.chat.id, message.as_str())
                         .parse_mode(ParseMode::MarkdownV2)
                         .await?;
                 }
             }
-            match user.admin_games.len() {
+            match admin_games.len() {
                 0 => {
                     bot.send_message(msg.chat.id, "There was no admin games found.")
                         .await?;
                 }
                 _ => {
                     let mut message: String = String::from("Here are your admin games:\n\n");
-                    for admin_game in user.admin_games {
-                        match runner.get_game(&admin_game) {
-                            Some(game) => {
-                                message.push_str(format!("{}\n", game).as_str());
-                            }
-                            None => {
-                                todo!()
-                            }
-                        }
-                    }
+                    admin_games
+                        .iter()
+                        .filter_map(|game_id| runner.get_game(game_id))
+                        .for_each(|game| message.push_str(format!("{}\n", game).as_str()));
+                    bot.send_message(msg.chat.id, message.as_str())
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .await?;
+                }
+            }
+            match past_games.len() {
+                0 => {
+                    bot.send_message(msg.chat.id, "There was no past games found.")
+                        .await?;
+                }
+                _ => {
+                    let mut message: String = String::from("Here are your past games:\n\n");
+                    past_games
+                        .iter()
+                        .filter_map(|game_id| runner.get_game(game_id))
+                        .for_each(|game| message.push_str(format!("{}\n", game).as_str()));
                     bot.send_message(msg.chat.id, message.as_str())
                         .parse_mode(ParseMode::MarkdownV2)
                         .await?;
@@ -412,183 +889,4355 @@ async fn list_cmd(bot: Bot, _dialogue: MyDialogue, msg: Message, runner: Runner)
     Ok(())
 }
 
-async fn info_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+async fn info_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = user_games(&runner, &UserId::from(msg.chat.id), |user| {
+        let mut game_ids = user.admin_games;
+        for game_id in user
+            .co_admin_games
+            .into_iter()
+            .chain(user.active_games)
+            .chain(user.pending_games)
+        {
+            if !game_ids.contains(&game_id) {
+                game_ids.push(game_id);
+            }
+        }
+        game_ids
+    })
+    .into_iter()
+    .filter(|game| game.status != GameStatus::Archived)
+    .collect();
+
+    if offer_games(
+        &bot,
+        msg.chat.id,
+        games,
+        Action::Info,
+        "Please choose the game you want to get info about.",
+    )
+    .await?
+    {
+        dialogue
+            .update(State::Info {
+                state: InfoState::GetId,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn exclude_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
     bot.send_message(
         msg.chat.id,
-        "Please enter id of the game you want to get info about.\n\
+        "Please enter code of the game you want to manage.\n\
             You can /cancel",
     )
     .await?;
     dialogue
-        .update(State::Info {
-            state: InfoState::GetId,
+        .update(State::Exclude {
+            state: ExcludeState::GetGameId,
         })
         .await?;
     Ok(())
 }
 
-async fn cancel(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
-    if let Some(cont) = dialogue.get().await.ok().unwrap() {
+async fn allow_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to manage.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Allow {
+            state: AllowState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn mode_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to manage.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Mode {
+            state: ModeState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn link_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to manage.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Link {
+            state: LinkState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn archive_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to archive.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Archive {
+            state: ArchiveState::GetId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn my_santa_cmd(
+    bot: Bot,
+    _dialogue: MyDialogue,
+    msg: Message,
+    runner: Runner,
+) -> HandlerResult {
+    let recipients = runner.get_recipients(&UserId::from(msg.chat.id));
+
+    match recipients.len() {
+        0 => {
+            bot.send_message(
+                msg.chat.id,
+                "It looks like you're not preparing presents in any game yet.",
+            )
+            .await?;
+        }
+        _ => {
+            let mut message = String::from("Here's who you're preparing presents for:\n\n");
+            for (game, recipient) in recipients {
+                let game_name = markdown::escape_code(&game.name);
+                let recipient_name = markdown::escape_code(&name_of(&runner, &recipient));
+                message.push_str(
+                    format!("Game: `{game_name}`\n Recipient: `{recipient_name}`\n").as_str(),
+                );
+                let wishlist = runner
+                    .get_user(&recipient)
+                    .and_then(|recipient| recipient.wishlist(&game.id).cloned());
+                if let Some(wishlist) = wishlist {
+                    message.push_str(markdown::escape(wishlist.to_string().as_str()).as_str());
+                }
+                message.push('\n');
+            }
+            bot.send_message(msg.chat.id, message)
+                .parse_mode(ParseMode::MarkdownV2)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn wishlist_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to make a wishlist for.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Wishlist {
+            state: WishlistState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn to_recipient_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game your recipient is in.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Relay {
+            state: RelayState::GetGameId {
+                direction: RelayDirection::ToRecipient,
+            },
+        })
+        .await?;
+    Ok(())
+}
+
+async fn to_santa_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game your secret santa is in.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Relay {
+            state: RelayState::GetGameId {
+                direction: RelayDirection::ToSanta,
+            },
+        })
+        .await?;
+    Ok(())
+}
+
+async fn budget_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to manage.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Budget {
+            state: BudgetState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn schedule_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to schedule.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Schedule {
+            state: ScheduleState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn lock_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to lock.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Lock {
+            state: LockState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn unlock_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to unlock.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Unlock {
+            state: UnlockState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn invite_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to invite people to.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Invite {
+            state: InviteState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn policy_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to manage.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Policy {
+            state: PolicyState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn grant_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = managed_games(&runner, &UserId::from(msg.chat.id), Permission::Owner);
+
+    if offer_games(
+        &bot,
+        msg.chat.id,
+        games,
+        Action::Grant,
+        "Please choose the game you want to get help with.",
+    )
+    .await?
+    {
+        dialogue
+            .update(State::Grant {
+                state: GrantState::GetGameId,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn revoke_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = managed_games(&runner, &UserId::from(msg.chat.id), Permission::Owner)
+        .into_iter()
+        .filter(|game| !game.co_admins.is_empty())
+        .collect();
+
+    if offer_games(
+        &bot,
+        msg.chat.id,
+        games,
+        Action::Revoke,
+        "Please choose the game you want to manage.",
+    )
+    .await?
+    {
+        dialogue
+            .update(State::Revoke {
+                state: RevokeState::GetGameId,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn transfer_cmd(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    runner: Runner,
+) -> HandlerResult {
+    let games = managed_games(&runner, &UserId::from(msg.chat.id), Permission::Owner);
+
+    if offer_games(
+        &bot,
+        msg.chat.id,
+        games,
+        Action::Transfer,
+        "Please choose the game you want to hand over.",
+    )
+    .await?
+    {
+        dialogue
+            .update(State::Transfer {
+                state: TransferState::GetGameId,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn delete_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = managed_games(&runner, &UserId::from(msg.chat.id), Permission::Owner)
+        .into_iter()
+        .filter(|game| !game.is_drawn())
+        .collect();
+
+    if offer_games(
+        &bot,
+        msg.chat.id,
+        games,
+        Action::Delete,
+        "Please choose the game you want to delete.",
+    )
+    .await?
+    {
+        dialogue
+            .update(State::Delete {
+                state: DeleteState::GetGameId,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn clone_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = managed_games(&runner, &UserId::from(msg.chat.id), Permission::Owner);
+
+    if offer_games(
+        &bot,
+        msg.chat.id,
+        games,
+        Action::Clone,
+        "Please choose the game you want to start again.",
+    )
+    .await?
+    {
+        dialogue
+            .update(State::Clone {
+                state: CloneState::GetGameId,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn repeat_cmd(bot: Bot, dialogue: MyDialogue, msg: Message, runner: Runner) -> HandlerResult {
+    let games = managed_games(
+        &runner,
+        &UserId::from(msg.chat.id),
+        Permission::EditSettings,
+    )
+    .into_iter()
+    .filter(|game| !game.is_drawn())
+    .collect();
+
+    if offer_games(
+        &bot,
+        msg.chat.id,
+        games,
+        Action::Repeat,
+        "Please choose the game that should repeat every year.",
+    )
+    .await?
+    {
+        dialogue
+            .update(State::Repeat {
+                state: RepeatState::GetGameId,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn party_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to set the party for.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Party {
+            state: PartyState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn remind_cmd(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Please enter code of the game you want to set reminders for.\n\
+            You can /cancel",
+    )
+    .await?;
+    dialogue
+        .update(State::Remind {
+            state: RemindState::GetGameId,
+        })
+        .await?;
+    Ok(())
+}
+
+async fn cancel(bot: Bot, dialogue: MyDialogue, msg: Message) -> HandlerResult {
+    if let Some(cont) = dialogue.get().await.ok().unwrap() {
         match cont {
             State::Register { state: _ } => {
                 bot.send_message(
                     msg.chat.id,
-                    "It's not possible to cancel the registration process..",
+                    "It's not possible to cancel the registration process..",
+                )
+                .await?;
+            }
+            _ => {
+                bot.send_message(msg.chat.id, "Cancelling the dialogue.")
+                    .await?;
+                dialogue.exit().await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn invalid_state(bot: Bot, msg: Message) -> HandlerResult {
+    bot.send_message(
+        msg.chat.id,
+        "Unable to handle the message. Type /help to see the usage.",
+    )
+    .await?;
+    Ok(())
+}
+
+async fn callback(
+    bot: Bot,
+    dialogue: MyDialogue,
+    q: CallbackQuery,
+    runner: Runner,
+) -> HandlerResult {
+    // stops the loading animation on the button
+    bot.answer_callback_query(q.id).await?;
+
+    let Some(message) = q.message else {
+        return Ok(());
+    };
+    let Some(action) = q.data.and_then(|data| data.parse::<Action>().ok()) else {
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
+
+    // every keyboard is used only once, so stale buttons can't be pressed again
+    bot.edit_message_reply_markup(chat_id, message.id).await?;
+
+    match action {
+        Action::Run(game_id) => {
+            match offer_run_confirmation(&bot, chat_id, &runner, game_id).await? {
+                true => {
+                    dialogue
+                        .update(State::Run {
+                            state: RunState::Confirm { game_id },
+                        })
+                        .await?;
+                }
+                false => {
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        Action::ConfirmRun(game_id) => {
+            run_game(&bot, chat_id, &runner, game_id).await?;
+            dialogue.exit().await?;
+        }
+        Action::Leave(game_id) => {
+            leave_game(&bot, chat_id, &runner, &game_id).await?;
+            dialogue.exit().await?;
+        }
+        Action::Accept(game_id) => {
+            match offer_pending_users(&bot, chat_id, &runner, game_id).await? {
+                true => {
+                    dialogue
+                        .update(State::Accept {
+                            state: AcceptState::GetUserId { game_id },
+                        })
+                        .await?;
+                }
+                false => {
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        Action::AcceptUser(game_id, user_id) => {
+            accept_user(&bot, chat_id, &runner, &game_id, &user_id).await?;
+            dialogue.exit().await?;
+        }
+        Action::Reject(game_id) => {
+            match offer_users_to_reject(&bot, chat_id, &runner, game_id).await? {
+                true => {
+                    dialogue
+                        .update(State::Reject {
+                            state: RejectState::ChooseUser { game_id },
+                        })
+                        .await?;
+                }
+                false => {
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        Action::RejectUser(game_id, user_id) => {
+            match ask_rejection_reason(&bot, chat_id, &runner, &game_id, &user_id).await? {
+                true => {
+                    dialogue
+                        .update(State::Reject {
+                            state: RejectState::GetReason { game_id, user_id },
+                        })
+                        .await?;
+                }
+                false => {
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        Action::AllowRejoin(game_id, user_id) => {
+            allow_rejoin(&bot, chat_id, &runner, &game_id, &user_id).await?;
+            dialogue.exit().await?;
+        }
+        Action::Grant(game_id) => {
+            match offer_users_to_grant(&bot, chat_id, &runner, game_id).await? {
+                true => {
+                    dialogue
+                        .update(State::Grant {
+                            state: GrantState::ChooseUser { game_id },
+                        })
+                        .await?;
+                }
+                false => {
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        Action::GrantUser(game_id, user_id) => {
+            match ask_permissions(&bot, chat_id, &runner, &game_id).await? {
+                true => {
+                    dialogue
+                        .update(State::Grant {
+                            state: GrantState::GetPermissions { game_id, user_id },
+                        })
+                        .await?;
+                }
+                false => {
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        Action::Revoke(game_id) => match offer_co_admins(&bot, chat_id, &runner, game_id).await? {
+            true => {
+                dialogue
+                    .update(State::Revoke {
+                        state: RevokeState::ChooseUser { game_id },
+                    })
+                    .await?;
+            }
+            false => {
+                dialogue.exit().await?;
+            }
+        },
+        Action::RevokeUser(game_id, user_id) => {
+            revoke_permissions(&bot, chat_id, &runner, &game_id, &user_id).await?;
+            dialogue.exit().await?;
+        }
+        Action::Transfer(game_id) => {
+            match offer_users_to_transfer(&bot, chat_id, &runner, game_id).await? {
+                true => {
+                    dialogue
+                        .update(State::Transfer {
+                            state: TransferState::ChooseUser { game_id },
+                        })
+                        .await?;
+                }
+                false => {
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        Action::TransferUser(game_id, user_id) => {
+            offer_transfer(&bot, chat_id, &runner, &game_id, &user_id).await?;
+            dialogue.exit().await?;
+        }
+        Action::AcceptTransfer(game_id) => {
+            match runner.accept_transfer(&game_id, &UserId::from(chat_id)) {
+                Ok(()) => {
+                    bot.send_message(
+                        chat_id,
+                        "Congratulations! You're the admin of this game now.\n\
+                        Use /info to see it and /help to see what you can do.",
+                    )
+                    .await?;
+                }
+                Err(_) => {
+                    bot.send_message(chat_id, "It looks like this offer isn't valid anymore")
+                        .await?;
+                }
+            }
+            dialogue.exit().await?;
+        }
+        Action::DeclineTransfer(game_id) => {
+            match runner.decline_transfer(&game_id, &UserId::from(chat_id)) {
+                Ok(()) => {
+                    bot.send_message(chat_id, "Ok, the admin will be told.")
+                        .await?;
+                }
+                Err(_) => {
+                    bot.send_message(chat_id, "It looks like this offer isn't valid anymore")
+                        .await?;
+                }
+            }
+            dialogue.exit().await?;
+        }
+        Action::Delete(game_id) => {
+            match offer_delete_confirmation(&bot, chat_id, &runner, game_id).await? {
+                true => {
+                    dialogue
+                        .update(State::Delete {
+                            state: DeleteState::Confirm { game_id },
+                        })
+                        .await?;
+                }
+                false => {
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        Action::ConfirmDelete(game_id) => {
+            delete_game(&bot, chat_id, &runner, &game_id).await?;
+            dialogue.exit().await?;
+        }
+        Action::Enrol(game_id) => {
+            enrol_admin(&bot, chat_id, &runner, &game_id).await?;
+            dialogue.exit().await?;
+        }
+        Action::Clone(game_id) => match ask_clone_name(&bot, chat_id, &runner, &game_id).await? {
+            true => {
+                dialogue
+                    .update(State::Clone {
+                        state: CloneState::GetName { game_id },
+                    })
+                    .await?;
+            }
+            false => {
+                dialogue.exit().await?;
+            }
+        },
+        Action::Repeat(game_id) => match ask_repeat_time(&bot, chat_id, &runner, &game_id).await? {
+            true => {
+                dialogue
+                    .update(State::Repeat {
+                        state: RepeatState::GetDateTime { game_id },
+                    })
+                    .await?;
+            }
+            false => {
+                dialogue.exit().await?;
+            }
+        },
+        Action::ConfirmInvitation(game_id) => {
+            match runner.accept_invitation(&UserId::from(chat_id), &game_id) {
+                Ok(()) => {
+                    bot.send_message(
+                        chat_id,
+                        "Great! You'll get a message once presents are distributed.",
+                    )
+                    .await?;
+                }
+                Err(_) => {
+                    bot.send_message(chat_id, "It looks like this invitation isn't valid anymore")
+                        .await?;
+                }
+            }
+            dialogue.exit().await?;
+        }
+        Action::DeclineInvitation(game_id) => {
+            let user_id = UserId::from(chat_id);
+            match runner.get_game(&game_id) {
+                Some(game) if game.invited_users.contains(&user_id) => {
+                    runner.remove_user_from_game(&user_id, &game_id, &user_id)?;
+                    bot.send_message(chat_id, "Ok, maybe next time!").await?;
+                }
+                _ => {
+                    bot.send_message(chat_id, "It looks like this invitation isn't valid anymore")
+                        .await?;
+                }
+            }
+            dialogue.exit().await?;
+        }
+        Action::Remove(game_id) => {
+            match offer_users_to_remove(&bot, chat_id, &runner, game_id).await? {
+                true => {
+                    dialogue
+                        .update(State::Remove {
+                            state: RemoveState::GetUserId { game_id },
+                        })
+                        .await?;
+                }
+                false => {
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        Action::RemoveUser(game_id, user_id) => {
+            remove_user(&bot, chat_id, &runner, &game_id, &user_id).await?;
+            dialogue.exit().await?;
+        }
+        Action::Info(game_id) => {
+            send_info(&bot, chat_id, &runner, &game_id).await?;
+            dialogue.exit().await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn register(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: RegisterState,
+    runner: Runner,
+) -> HandlerResult {
+    let RegisterState::GetName { invite } = state;
+
+    match msg.text().map(ToOwned::to_owned) {
+        Some(name) => match runner.new_user(msg.chat.id.into(), name.clone()).is_ok() {
+            true => {
+                bot.send_message(
+                    msg.chat.id,
+                    format! {"Thanks for completing the registration, {name}.\n\
+                        You can change your username using /username\n\
+                    Use /help to get more info."},
+                )
+                .await?;
+                dialogue.exit().await?;
+
+                if let Some(game_id) = invite {
+                    join_game(
+                        &bot,
+                        &dialogue,
+                        msg.chat.id,
+                        &runner,
+                        &game_id,
+                        JoinRequest::Invite,
+                    )
+                    .await?;
+                }
+            }
+            false => {
+                bot.send_message(
+                    msg.chat.id,
+                    "It looks like you're already registered.\n\
+                        You can change your username using /username",
+                )
+                .await?;
+                dialogue.exit().await?;
+            }
+        },
+        None => {
+            bot.send_message(msg.chat.id, "Please use /help").await?;
+            dialogue.exit().await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn username(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    _state: UsernameState,
+    runner: Runner,
+) -> HandlerResult {
+    match msg.text().map(ToOwned::to_owned) {
+        Some(name) => {
+            match runner
+                .change_username(&UserId::from(msg.chat.id), name.clone())
+                .is_ok()
+            {
+                true => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format! {"You've changed your username to {name}."},
+                    )
+                    .await?;
+                }
+                false => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "It looks like you're not registered. Please register with /start",
+                    )
+                    .await?;
+                }
+            }
+
+            dialogue.exit().await?;
+        }
+        None => {
+            bot.send_message(msg.chat.id, "Please use /help").await?;
+            dialogue.exit().await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn create(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: CreateState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        CreateState::GetName => match msg.text().map(ToOwned::to_owned) {
+            Some(game_name) => {
+                bot.send_message(msg.chat.id, DRAW_MODE_QUESTION)
+                    .parse_mode(ParseMode::MarkdownV2)
+                    .await?;
+                dialogue
+                    .update(State::Create {
+                        state: CreateState::ChooseDrawMode { game_name },
+                    })
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        CreateState::ChooseDrawMode { game_name } => {
+            match msg.text().map(|text| text.parse::<DrawMode>()) {
+                Some(Ok(draw_mode)) => {
+                    bot.send_message(msg.chat.id, BUDGET_QUESTION).await?;
+                    dialogue
+                        .update(State::Create {
+                            state: CreateState::GetBudget {
+                                game_name,
+                                draw_mode,
+                            },
+                        })
+                        .await?;
+                }
+                Some(Err(())) => {
+                    bot.send_message(msg.chat.id, "Please answer pairs or chain or use /cancel")
+                        .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        CreateState::GetBudget {
+            game_name,
+            draw_mode,
+        } => match msg.text().map(parse_budget) {
+            Some(Ok(budget)) => {
+                bot.send_message(msg.chat.id, PARTICIPATION_QUESTION)
+                    .await?;
+                dialogue
+                    .update(State::Create {
+                        state: CreateState::ChooseParticipation {
+                            game_name,
+                            draw_mode,
+                            budget,
+                        },
+                    })
+                    .await?;
+            }
+            Some(Err(())) => {
+                bot.send_message(
+                    msg.chat.id,
+                    "Unable to understand this budget.\n Please retry or use /cancel",
+                )
+                .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        CreateState::ChooseParticipation {
+            game_name,
+            draw_mode,
+            budget,
+        } => match msg.text().map(|text| text.trim().to_lowercase()) {
+            Some(answer) if answer == "yes" || answer == "no" => {
+                match runner.new_game(
+                    UserId::from(msg.chat.id),
+                    game_name,
+                    draw_mode,
+                    budget,
+                    answer == "yes",
+                ) {
+                    Ok(game_id) => {
+                        announce_game(&bot, msg.chat.id, &runner, &game_id).await?;
+                        dialogue.exit().await?;
+                    }
+                    Err(error) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            format!("Unable to create this game.\n{error}"),
+                        )
+                        .await?;
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            Some(_) => {
+                bot.send_message(msg.chat.id, "Please answer yes or no or use /cancel")
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn run(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: RunState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        RunState::GetId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match offer_run_confirmation(&bot, msg.chat.id, &runner, game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Run {
+                                state: RunState::Confirm { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        RunState::Confirm { game_id } => match msg.text().map(ToOwned::to_owned) {
+            Some(text) => {
+                let id = game_id.0;
+                match text == format!("Yes, I do want to run game {id}") {
+                    true => {
+                        run_game(&bot, msg.chat.id, &runner, game_id).await?;
+                        dialogue.exit().await?;
+                    }
+                    false => {
+                        bot.send_message(msg.chat.id, "Text doesn't match confirnation statement.\n Please retry or use /cancel").await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// asks admin to confirm the draw, returns false if the game can't be run
+async fn offer_run_confirmation(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, &game_id, Permission::Run).await? else {
+        return Ok(false);
+    };
+    if game.is_drawn() {
+        bot.send_message(chat_id, "It looks like this game was already drawn")
+            .await?;
+        return Ok(false);
+    }
+
+    let id = game_id.0;
+    let name = markdown::escape_code(&game.name);
+    let keyboard = InlineKeyboardMarkup::new([[InlineKeyboardButton::callback(
+        "Yes, run it",
+        Action::ConfirmRun(game_id).to_string(),
+    )]]);
+    bot.send_message(
+        chat_id,
+        format! {"Please confirm that you're going to run game `{name}`\n\
+        This action is irreversible\n\
+        Messages about who to give the gift to will be sent out instantly\n\n\
+        To confirm please press the button or type `Yes, I do want to run game {id}`\n\
+        You can /cancel"},
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .reply_markup(keyboard)
+    .await?;
+
+    Ok(true)
+}
+
+async fn run_game(bot: &Bot, chat_id: ChatId, runner: &Runner, game_id: GameId) -> HandlerResult {
+    if authorized_game(bot, chat_id, runner, &game_id, Permission::Run)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    // the draw may take a while, so it doesn't block other handlers
+    let draw_runner = runner.clone();
+    match tokio::task::spawn_blocking(move || draw_runner.run_game(game_id)).await? {
+        Ok(()) => {
+            bot.send_message(
+                chat_id,
+                "You've successfully ran this game.\n\
+                    Messages will be sent shortly, you'll get a report once they're delivered\n\
+                    Thanks for using this bot!",
+            )
+            .await?;
+        }
+        Err(error) => {
+            bot.send_message(chat_id, format!("Unable to run this game.\n{error}"))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn join(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: JoinState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        JoinState::GetId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                join_game(
+                    &bot,
+                    &dialogue,
+                    msg.chat.id,
+                    &runner,
+                    &game_id,
+                    JoinRequest::Code,
+                )
+                .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        JoinState::GetPassword { game_id } => match msg.text().map(ToOwned::to_owned) {
+            Some(password) => {
+                join_game(
+                    &bot,
+                    &dialogue,
+                    msg.chat.id,
+                    &runner,
+                    &game_id,
+                    JoinRequest::Password(password.trim().to_owned()),
+                )
+                .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// adds user to the game and tells them how it went,
+/// dialogue is left unless user has to enter the password of the game
+async fn join_game(
+    bot: &Bot,
+    dialogue: &MyDialogue,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    request: JoinRequest,
+) -> HandlerResult {
+    let user_id = UserId::from(chat_id);
+    let with_password = matches!(request, JoinRequest::Password(_));
+
+    match runner.get_game(game_id) {
+        Some(_) => match runner.add_user_to_pending(&user_id, game_id, request) {
+            Ok(JoinOutcome::Pending) => {
+                bot.send_message(
+                    chat_id,
+                    "You're now in the waiting list to this game.\n\
+                Please wait until game administrator confirms you.\n\
+                You can /leave to leave game and /list to list all your games.",
+                )
+                .await?;
+            }
+            Ok(JoinOutcome::Active) => {
+                bot.send_message(
+                    chat_id,
+                    "You've joined this game!\n\
+                You'll get a message once presents are distributed.\n\
+                You can /leave to leave game and /list to list all your games.",
+                )
+                .await?;
+            }
+            Err(error) if error.is::<WrongPasswordError>() => {
+                match with_password {
+                    true => {
+                        bot.send_message(chat_id, "Wrong password.\n Please retry or use /cancel")
+                            .await?;
+                    }
+                    false => {
+                        bot.send_message(
+                            chat_id,
+                            "This game is protected with a password.\n\
+                        Please enter it or use /cancel",
+                        )
+                        .await?;
+                    }
+                }
+                dialogue
+                    .update(State::Join {
+                        state: JoinState::GetPassword { game_id: *game_id },
+                    })
+                    .await?;
+                return Ok(());
+            }
+            Err(error) if error.is::<UserIsRejectedError>() => {
+                bot.send_message(
+                    chat_id,
+                    "Sorry, game administrator has rejected your request to join this game.",
+                )
+                .await?;
+            }
+            Err(error) if error.is::<GameIsInviteOnlyError>() => {
+                bot.send_message(
+                    chat_id,
+                    "Sorry, this game can be joined only with an invite link.\n\
+                Please ask game administrator for it.",
+                )
+                .await?;
+            }
+            Err(error) if error.is::<GameIsLockedError>() => {
+                bot.send_message(
+                    chat_id,
+                    "Sorry, registration to this game is closed.\n\
+                Please ask game administrator to /unlock it if you'd like to take part.",
+                )
+                .await?;
+            }
+            Err(error) if error.is::<GameIsAlreadyDrawnError>() => {
+                bot.send_message(
+                    chat_id,
+                    "Sorry, presents in this game were already distributed.",
+                )
+                .await?;
+            }
+            Err(_) => {
+                bot.send_message(
+                    chat_id,
+                    "It looks like you're already in this game.\n\
+                You can /list to list all your games.",
+                )
+                .await?;
+            }
+        },
+        None => {
+            bot.send_message(
+                chat_id,
+                "It looks like there's no such game.\n\
+            Please use /help",
+            )
+            .await?;
+        }
+    }
+
+    dialogue.exit().await?;
+
+    Ok(())
+}
+
+async fn invite(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    _state: InviteState,
+    runner: Runner,
+) -> HandlerResult {
+    match msg.text().map(ToOwned::to_owned) {
+        Some(game_id) => {
+            let Some(game_id) = runner.find_game(&game_id) else {
+                bot.send_message(msg.chat.id, "It looks like there's no such game")
+                    .await?;
+                dialogue.exit().await?;
+                return Ok(());
+            };
+            let user_id = UserId::from(msg.chat.id);
+
+            match runner.get_game(&game_id) {
+                Some(game) => match game.allows(&user_id, Permission::Approve) {
+                    true => {
+                        let link = invite_link(&bot, &runner, &game_id).await?;
+                        let joins = match game.join_policy {
+                            JoinPolicy::Manual => "joins the waiting list of this game",
+                            JoinPolicy::AutoAccept | JoinPolicy::InviteOnly => {
+                                "becomes a participant of this game right away"
+                            }
+                            JoinPolicy::Password(_) => "can join this game with its password",
+                        };
+                        bot.send_message(
+                            msg.chat.id,
+                            format!("Anyone who opens this link {joins}:\n{link}"),
+                        )
+                        .await?;
+                    }
+                    false => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "It looks like you're not allowed to do this in this game",
+                        )
+                        .await?;
+                    }
+                },
+                None => {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                }
+            }
+
+            dialogue.exit().await?;
+        }
+        None => {
+            bot.send_message(msg.chat.id, "Please use /help").await?;
+            dialogue.exit().await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn leave(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    _state: LeaveState,
+    runner: Runner,
+) -> HandlerResult {
+    match msg.text().map(ToOwned::to_owned) {
+        Some(game_id) => {
+            let Some(game_id) = runner.find_game(&game_id) else {
+                bot.send_message(msg.chat.id, "It looks like there's no such game")
+                    .await?;
+                dialogue.exit().await?;
+                return Ok(());
+            };
+
+            leave_game(&bot, msg.chat.id, &runner, &game_id).await?;
+            dialogue.exit().await?;
+        }
+        None => {
+            bot.send_message(msg.chat.id, "Please use /help").await?;
+            dialogue.exit().await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn leave_game(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+) -> HandlerResult {
+    let user_id = UserId::from(chat_id);
+    match runner.remove_user_from_game(&user_id, game_id, &user_id) {
+        Ok(()) => {
+            bot.send_message(chat_id, "You've successfully left this game.")
+                .await?;
+        }
+        Err(error) if error.is::<GameIsAlreadyDrawnError>() => {
+            bot.send_message(
+                chat_id,
+                "Sorry, presents in this game were already distributed.",
+            )
+            .await?;
+        }
+        Err(_) => {
+            bot.send_message(chat_id, "It looks like you're not in this game")
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn accept(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: AcceptState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        AcceptState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match offer_pending_users(&bot, msg.chat.id, &runner, game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Accept {
+                                state: AcceptState::GetUserId { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        AcceptState::GetUserId { game_id } => {
+            match msg.text().map(|text| text.trim().parse::<i64>()) {
+                Some(Ok(user_id)) => {
+                    accept_user(&bot, msg.chat.id, &runner, &game_id, &UserId(user_id)).await?;
+                    dialogue.exit().await?;
+                }
+                Some(Err(_)) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Please press one of the buttons above or use /cancel",
+                    )
+                    .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn offer_pending_users(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, &game_id, Permission::Approve).await?
+    else {
+        return Ok(false);
+    };
+
+    offer_users(
+        bot,
+        chat_id,
+        runner,
+        &game.pending_users,
+        |user_id| Action::AcceptUser(game_id, user_id),
+        "Please choose the user to accept.",
+    )
+    .await
+}
+
+async fn accept_user(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    user_id: &UserId,
+) -> HandlerResult {
+    if authorized_game(bot, chat_id, runner, game_id, Permission::Approve)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    match runner.promote_user_from_pending_to_active(user_id, game_id) {
+        Ok(()) => {
+            bot.send_message(chat_id, "You've accepted this user to the game.")
+                .await?;
+        }
+        Err(_) => {
+            bot.send_message(chat_id, "It looks like this user isn't waiting to join")
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn reject(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: RejectState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        RejectState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match offer_users_to_reject(&bot, msg.chat.id, &runner, game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Reject {
+                                state: RejectState::ChooseUser { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        RejectState::ChooseUser { game_id } => {
+            match msg.text().map(|text| text.trim().parse::<i64>()) {
+                Some(Ok(user_id)) => {
+                    let user_id = UserId(user_id);
+                    match ask_rejection_reason(&bot, msg.chat.id, &runner, &game_id, &user_id)
+                        .await?
+                    {
+                        true => {
+                            dialogue
+                                .update(State::Reject {
+                                    state: RejectState::GetReason { game_id, user_id },
+                                })
+                                .await?;
+                        }
+                        false => {
+                            dialogue.exit().await?;
+                        }
+                    }
+                }
+                Some(Err(_)) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Please press one of the buttons above or use /cancel",
+                    )
+                    .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        RejectState::GetReason { game_id, user_id } => {
+            match msg.text().map(|text| text.trim().to_owned()) {
+                Some(reason) => {
+                    // "-" means there's no reason
+                    let reason = match reason.as_str() {
+                        "-" => None,
+                        _ => Some(reason),
+                    };
+                    reject_user(&bot, msg.chat.id, &runner, &game_id, &user_id, reason).await?;
+                    dialogue.exit().await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn offer_users_to_reject(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, &game_id, Permission::Approve).await?
+    else {
+        return Ok(false);
+    };
+
+    offer_users(
+        bot,
+        chat_id,
+        runner,
+        &game.pending_users,
+        |user_id| Action::RejectUser(game_id, user_id),
+        "Please choose the user to reject.",
+    )
+    .await
+}
+
+/// returns false if this user can't be rejected
+async fn ask_rejection_reason(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    user_id: &UserId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, game_id, Permission::Approve).await?
+    else {
+        return Ok(false);
+    };
+    if !game.pending_users.contains(user_id) {
+        bot.send_message(chat_id, "It looks like this user isn't waiting to join")
+            .await?;
+        return Ok(false);
+    }
+
+    bot.send_message(
+        chat_id,
+        "Why are you rejecting them? They'll get your answer.\n\
+            Send - to reject without a reason.\n\
+            You can /cancel",
+    )
+    .await?;
+
+    Ok(true)
+}
+
+async fn reject_user(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    user_id: &UserId,
+    reason: Option<String>,
+) -> HandlerResult {
+    if authorized_game(bot, chat_id, runner, game_id, Permission::Approve)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    match runner.reject_user(user_id, game_id, reason) {
+        Ok(()) => {
+            let keyboard = InlineKeyboardMarkup::new([[InlineKeyboardButton::callback(
+                "Allow them to ask again",
+                Action::AllowRejoin(*game_id, *user_id).to_string(),
+            )]]);
+            bot.send_message(
+                chat_id,
+                "You've rejected this user, they won't be able to ask to join again.",
+            )
+            .reply_markup(keyboard)
+            .await?;
+        }
+        Err(_) => {
+            bot.send_message(chat_id, "It looks like this user isn't waiting to join")
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn allow_rejoin(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    user_id: &UserId,
+) -> HandlerResult {
+    if authorized_game(bot, chat_id, runner, game_id, Permission::Approve)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    match runner.allow_rejoin(user_id, game_id) {
+        Ok(()) => {
+            bot.send_message(chat_id, "This user can ask to join this game again.")
+                .await?;
+        }
+        Err(error) => {
+            bot.send_message(chat_id, format!("Unable to allow this.\n{error}"))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn remove(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: RemoveState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        RemoveState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match offer_users_to_remove(&bot, msg.chat.id, &runner, game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Remove {
+                                state: RemoveState::GetUserId { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        RemoveState::GetUserId { game_id } => {
+            match msg.text().map(|text| text.trim().parse::<i64>()) {
+                Some(Ok(user_id)) => {
+                    remove_user(&bot, msg.chat.id, &runner, &game_id, &UserId(user_id)).await?;
+                    dialogue.exit().await?;
+                }
+                Some(Err(_)) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Please press one of the buttons above or use /cancel",
+                    )
+                    .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn offer_users_to_remove(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, &game_id, Permission::Remove).await?
+    else {
+        return Ok(false);
+    };
+
+    offer_users(
+        bot,
+        chat_id,
+        runner,
+        &[game.pending_users, game.active_users].concat(),
+        |user_id| Action::RemoveUser(game_id, user_id),
+        "Please choose the user to remove.",
+    )
+    .await
+}
+
+async fn remove_user(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    user_id: &UserId,
+) -> HandlerResult {
+    if authorized_game(bot, chat_id, runner, game_id, Permission::Remove)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    match runner.remove_user_from_game(user_id, game_id, &UserId::from(chat_id)) {
+        Ok(()) => {
+            bot.send_message(chat_id, "You've removed this user from the game.")
+                .await?;
+        }
+        Err(error) if error.is::<GameIsAlreadyDrawnError>() => {
+            bot.send_message(
+                chat_id,
+                "It's too late, presents in this game were already distributed.",
+            )
+            .await?;
+        }
+        Err(_) => {
+            bot.send_message(chat_id, "It looks like there's no such user")
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn info(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    _state: InfoState,
+    runner: Runner,
+) -> HandlerResult {
+    match msg.text().map(ToOwned::to_owned) {
+        Some(game_id) => {
+            let Some(game_id) = runner.find_game(&game_id) else {
+                bot.send_message(msg.chat.id, "It looks like there's no such game")
+                    .await?;
+                dialogue.exit().await?;
+                return Ok(());
+            };
+            send_info(&bot, msg.chat.id, &runner, &game_id).await?;
+
+            dialogue.exit().await?;
+        }
+        None => {
+            bot.send_message(msg.chat.id, "Please use /help").await?;
+            dialogue.exit().await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_info(bot: &Bot, chat_id: ChatId, runner: &Runner, game_id: &GameId) -> HandlerResult {
+    let Some(game) = runner.get_game(game_id) else {
+        bot.send_message(chat_id, "It looks like there's no such game")
+            .await?;
+        return Ok(());
+    };
+    let user_id = UserId::from(chat_id);
+    // co-admins see everything about the game that they're allowed to change
+    let manages = [
+        Permission::Approve,
+        Permission::Remove,
+        Permission::EditSettings,
+        Permission::Run,
+    ]
+    .into_iter()
+    .any(|permission| game.allows(&user_id, permission));
+    if !manages && !game.active_users.contains(&user_id) && !game.pending_users.contains(&user_id) {
+        bot.send_message(chat_id, "It looks like you're not in this game")
+            .await?;
+        return Ok(());
+    }
+
+    let mut message = String::from("Here are info about your game:\n\n");
+
+    let game_name = markdown::escape_code(&game.name);
+    message.push_str(format!("Name: `{game_name}`\n\n").as_str());
+
+    if let Some(code) = &game.code {
+        message.push_str(format!("Code: `{code}`\n\n").as_str());
+    }
+
+    if manages {
+        let game_id = game_id.0;
+        message.push_str(format!("Id: `{game_id}`\n\n").as_str());
+    }
+
+    let status = game.status;
+    message.push_str(format!("Status: `{status}`\n\n").as_str());
+
+    if let Some(budget) = &game.budget {
+        let budget = markdown::escape_code(&budget.to_string());
+        message.push_str(format!("Budget: `{budget}`\n\n").as_str());
+    }
+
+    if let Some(closes_at) = game.registration_closes_at {
+        message.push_str(format!("Registration closes: `{closes_at}`\n\n").as_str());
+    }
+
+    if let Some(draw_at) = game.draw_at {
+        message.push_str(format!("Scheduled draw: `{draw_at}`\n\n").as_str());
+    }
+
+    if let Some(party_at) = game.party_at {
+        message.push_str(format!("Party: `{party_at}`\n\n").as_str());
+    }
+
+    if !game.reminders.is_empty() {
+        message.push_str("Reminders:\n");
+        for reminder in &game.reminders {
+            message.push_str(describe_reminder(reminder).as_str());
+        }
+        message.push('\n');
+    }
+
+    let draw_mode = game.draw_mode;
+    message.push_str(format!("Draw mode: `{draw_mode}`\n\n").as_str());
+
+    let join_policy = &game.join_policy;
+    message.push_str(format!("Join policy: `{join_policy}`\n\n").as_str());
+
+    if let Some(recurrence) = game.recurrence {
+        let draw_at = recurrence.draw_at();
+        message.push_str(format!("Repeats every year: `{draw_at}`\n\n").as_str());
+    }
+
+    let mut previous_game = game.previous_game;
+    let mut previous_names = vec![];
+    while let Some(assignments) = previous_game.and_then(|game_id| runner.get_assignments(&game_id))
+    {
+        previous_names.push(format!(
+            "`{}`",
+            markdown::escape_code(&assignments.game_name)
+        ));
+        previous_game = assignments.previous_game;
+    }
+    match previous_names.len() {
+        0 => {}
+        1 => {
+            let previous_name = &previous_names[0];
+            message.push_str(format!("Previous edition: {previous_name}\n\n").as_str());
+        }
+        _ => {
+            let previous_names = previous_names.join(", ");
+            message.push_str(format!("Previous editions: {previous_names}\n\n").as_str());
+        }
+    }
+
+    message.push_str("Active users:\n");
+
+    // participants see each other by name only
+    if !manages {
+        game.active_users.iter().for_each(|id| {
+            let username = markdown::escape_code(&name_of(runner, id));
+            message.push_str(format!("Name: `{username}`\n\n").as_str())
+        });
+
+        return send_info_message(bot, chat_id, &game, message).await;
+    }
+
+    game.active_users
+        .iter()
+        .for_each(|id| message.push_str(describe_user(runner, id).as_str()));
+
+    message.push_str("\nPending users:\n");
+
+    game.pending_users
+        .iter()
+        .for_each(|id| message.push_str(describe_user(runner, id).as_str()));
+
+    if !game.co_admins.is_empty() {
+        message.push_str("\nCo\\-admins:\n");
+
+        for co_admin in &game.co_admins {
+            message.push_str(describe_user(runner, &co_admin.user_id).as_str());
+            let permissions: Vec<String> = co_admin
+                .permissions
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            message.push_str(format!(" Can: `{}`\n\n", permissions.join(", ")).as_str());
+        }
+    }
+
+    if let Some(user_id) = game.transfer_to {
+        message.push_str("\nOffered to become admin:\n");
+        message.push_str(describe_user(runner, &user_id).as_str());
+    }
+
+    if !game.rejections.is_empty() && game.allows(&user_id, Permission::Approve) {
+        message.push_str("\nRejected users:\n");
+
+        for rejection in &game.rejections {
+            message.push_str(describe_user(runner, &rejection.user_id).as_str());
+            if let Some(reason) = &rejection.reason {
+                let reason = markdown::escape(reason);
+                message.push_str(format!(" Reason: {reason}\n\n").as_str());
+            }
+        }
+    }
+
+    let undelivered: Vec<UserId> = runner
+        .game_messages(&game.id)
+        .into_iter()
+        .filter(|message| matches!(message.status, DeliveryStatus::Failed { .. }))
+        .map(|message| message.recipient)
+        .collect();
+    if !undelivered.is_empty() && game.allows(&user_id, Permission::Run) {
+        message.push_str("\nUnable to deliver messages to:\n");
+
+        undelivered
+            .iter()
+            .for_each(|id| message.push_str(describe_user(runner, id).as_str()));
+    }
+
+    if !game.exclusions.is_empty() && game.allows(&user_id, Permission::EditSettings) {
+        message.push_str("\nExclusion rules:\n");
+
+        game.exclusions
+            .iter()
+            .for_each(|exclusion| message.push_str(describe_exclusion(runner, exclusion).as_str()));
+    }
+
+    send_info_message(bot, chat_id, &game, message).await
+}
+
+async fn send_info_message(
+    bot: &Bot,
+    chat_id: ChatId,
+    game: &Game,
+    message: String,
+) -> HandlerResult {
+    let mut request = bot
+        .send_message(chat_id, message)
+        .parse_mode(ParseMode::MarkdownV2);
+    if game.admin == UserId::from(chat_id)
+        && !game.active_users.contains(&game.admin)
+        && !game.is_drawn()
+    {
+        request = request.reply_markup(InlineKeyboardMarkup::new([[
+            InlineKeyboardButton::callback(
+                "Take part yourself",
+                Action::Enrol(game.id).to_string(),
+            ),
+        ]]));
+    }
+    request.await?;
+
+    Ok(())
+}
+
+async fn enrol_admin(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+) -> HandlerResult {
+    if authorized_game(bot, chat_id, runner, game_id, Permission::Owner)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    match runner.enrol_admin(game_id) {
+        Ok(()) => {
+            bot.send_message(chat_id, "You're taking part in this game now.")
+                .await?;
+        }
+        Err(error) => {
+            bot.send_message(chat_id, format!("Unable to join this game.\n{error}"))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn exclude(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: ExcludeState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        ExcludeState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                let user_id = UserId::from(msg.chat.id);
+
+                match runner.get_game(&game_id) {
+                    Some(game) => match game.allows(&user_id, Permission::EditSettings) {
+                        true => {
+                            let mut message = String::from("Here are all users:\n\n");
+
+                            game.active_users
+                                .iter()
+                                .chain(game.pending_users.iter())
+                                .for_each(|id| {
+                                    message.push_str(describe_user(&runner, id).as_str())
+                                });
+
+                            bot.send_message(msg.chat.id, message)
+                                .parse_mode(ParseMode::MarkdownV2)
+                                .await?;
+
+                            bot.send_message(
+                                msg.chat.id,
+                                "Please send id of the user who shouldn't prepare a present.",
+                            )
+                            .await?;
+
+                            dialogue
+                                .update(State::Exclude {
+                                    state: ExcludeState::GetPresenterId { game_id },
+                                })
+                                .await?;
+                        }
+                        false => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "It looks like you're not allowed to do this in this game",
+                            )
+                            .await?;
+                            dialogue.exit().await?;
+                        }
+                    },
+                    None => {
+                        bot.send_message(msg.chat.id, "It looks like there's no such game")
+                            .await?;
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        ExcludeState::GetPresenterId { game_id } => match msg.text() {
+            Some(presenter) => {
+                let Some(presenter) = parse_game_user(&runner, &game_id, presenter) else {
+                    bot.send_message(
+                        msg.chat.id,
+                        "There's no user with such id in this game.\n Please retry or use /cancel",
+                    )
+                    .await?;
+                    return Ok(());
+                };
+
+                bot.send_message(
+                    msg.chat.id,
+                    "Please send id of the user who shouldn't receive a present from them.",
+                )
+                .await?;
+
+                dialogue
+                    .update(State::Exclude {
+                        state: ExcludeState::GetRecipientId { game_id, presenter },
+                    })
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        ExcludeState::GetRecipientId { game_id, presenter } => match msg.text() {
+            Some(recipient) => {
+                let Some(recipient) = parse_game_user(&runner, &game_id, recipient) else {
+                    bot.send_message(
+                        msg.chat.id,
+                        "There's no user with such id in this game.\n Please retry or use /cancel",
+                    )
+                    .await?;
+                    return Ok(());
+                };
+
+                bot.send_message(
+                    msg.chat.id,
+                    "Should this rule work both ways?\n\
+                            Please answer `yes` or `no`",
+                )
+                .parse_mode(ParseMode::MarkdownV2)
+                .await?;
+
+                dialogue
+                    .update(State::Exclude {
+                        state: ExcludeState::ChooseDirection {
+                            game_id,
+                            presenter,
+                            recipient,
+                        },
+                    })
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        ExcludeState::ChooseDirection {
+            game_id,
+            presenter,
+            recipient,
+        } => match msg.text().map(|text| text.trim().to_lowercase()) {
+            Some(answer) if answer == "yes" || answer == "no" => {
+                let exclusion = match answer == "yes" {
+                    true => Exclusion::BothWays(presenter, recipient),
+                    false => Exclusion::OneWay {
+                        presenter,
+                        recipient,
+                    },
+                };
+
+                match runner.add_exclusion(&game_id, exclusion) {
+                    Ok(()) => {
+                        bot.send_message(msg.chat.id, "You've added this exclusion rule.")
+                            .await?;
+                    }
+                    Err(error) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            format!("Unable to add this exclusion rule.\n{error}"),
+                        )
+                        .await?;
+                    }
+                }
+                dialogue.exit().await?;
+            }
+            Some(_) => {
+                bot.send_message(msg.chat.id, "Please answer yes or no or use /cancel")
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn allow(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: AllowState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        AllowState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                let user_id = UserId::from(msg.chat.id);
+
+                match runner.get_game(&game_id) {
+                    Some(game) if !game.allows(&user_id, Permission::EditSettings) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "It looks like you're not allowed to do this in this game",
+                        )
+                        .await?;
+                        dialogue.exit().await?;
+                    }
+                    Some(game) if game.exclusions.is_empty() => {
+                        bot.send_message(msg.chat.id, "This game has no exclusion rules.")
+                            .await?;
+                        dialogue.exit().await?;
+                    }
+                    Some(game) => {
+                        let mut message = String::from("Here are all exclusion rules:\n\n");
+
+                        game.exclusions
+                            .iter()
+                            .enumerate()
+                            .for_each(|(number, exclusion)| {
+                                message.push_str(
+                                    format!(
+                                        "{}: {}",
+                                        number + 1,
+                                        describe_exclusion(&runner, exclusion)
+                                    )
+                                    .as_str(),
+                                )
+                            });
+
+                        bot.send_message(msg.chat.id, message)
+                            .parse_mode(ParseMode::MarkdownV2)
+                            .await?;
+
+                        bot.send_message(msg.chat.id, "Please send number of the rule to remove.")
+                            .await?;
+
+                        dialogue
+                            .update(State::Allow {
+                                state: AllowState::GetNumber { game_id },
+                            })
+                            .await?;
+                    }
+                    None => {
+                        bot.send_message(msg.chat.id, "It looks like there's no such game")
+                            .await?;
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        AllowState::GetNumber { game_id } => {
+            let exclusion = msg
+                .text()
+                .and_then(|text| text.trim().parse::<usize>().ok())
+                .and_then(|number| number.checked_sub(1))
+                .and_then(|index| runner.get_game(&game_id)?.exclusions.get(index).copied());
+
+            match exclusion {
+                Some(exclusion) => {
+                    match runner.remove_exclusion(&game_id, &exclusion) {
+                        Ok(()) => {
+                            bot.send_message(msg.chat.id, "You've removed this exclusion rule.")
+                                .await?;
+                        }
+                        Err(error) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Unable to remove this exclusion rule.\n{error}"),
+                            )
+                            .await?;
+                        }
+                    }
+                    dialogue.exit().await?;
+                }
+                None => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "There's no rule with such number.\n Please retry or use /cancel",
+                    )
+                    .await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn mode(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: ModeState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        ModeState::GetGameId => {
+            match msg.text().map(ToOwned::to_owned) {
+                Some(game_id) => {
+                    let Some(game_id) = runner.find_game(&game_id) else {
+                        bot.send_message(msg.chat.id, "It looks like there's no such game")
+                            .await?;
+                        dialogue.exit().await?;
+                        return Ok(());
+                    };
+                    let user_id = UserId::from(msg.chat.id);
+
+                    match runner.get_game(&game_id) {
+                        Some(game) => match game.allows(&user_id, Permission::EditSettings) {
+                            true => {
+                                let draw_mode = game.draw_mode;
+                                bot.send_message(
+                                msg.chat.id,
+                                format!("Current draw mode is `{draw_mode}`\\.\n\n{DRAW_MODE_QUESTION}"),
+                            )
+                            .parse_mode(ParseMode::MarkdownV2)
+                            .await?;
+
+                                dialogue
+                                    .update(State::Mode {
+                                        state: ModeState::GetDrawMode { game_id },
+                                    })
+                                    .await?;
+                            }
+                            false => {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    "It looks like you're not allowed to do this in this game",
+                                )
+                                .await?;
+                                dialogue.exit().await?;
+                            }
+                        },
+                        None => {
+                            bot.send_message(msg.chat.id, "It looks like there's no such game")
+                                .await?;
+                            dialogue.exit().await?;
+                        }
+                    }
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        ModeState::GetDrawMode { game_id } => match msg.text().map(|text| text.parse::<DrawMode>())
+        {
+            Some(Ok(draw_mode)) => {
+                match runner.set_draw_mode(&game_id, draw_mode) {
+                    Ok(()) => {
+                        bot.send_message(msg.chat.id, format!("Draw mode is now {draw_mode}."))
+                            .await?;
+                    }
+                    Err(error) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            format!("Unable to change the draw mode.\n{error}"),
+                        )
+                        .await?;
+                    }
+                }
+                dialogue.exit().await?;
+            }
+            Some(Err(())) => {
+                bot.send_message(msg.chat.id, "Please answer pairs or chain or use /cancel")
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn link(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: LinkState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        LinkState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                let user_id = UserId::from(msg.chat.id);
+
+                match runner.get_game(&game_id) {
+                    Some(game) => match game.allows(&user_id, Permission::EditSettings) {
+                        true => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "Please enter code of the previous edition of this game.\n\
+                                    Pairs from it won't be repeated if possible.",
+                            )
+                            .await?;
+
+                            dialogue
+                                .update(State::Link {
+                                    state: LinkState::GetPreviousGameId { game_id },
+                                })
+                                .await?;
+                        }
+                        false => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "It looks like you're not allowed to do this in this game",
+                            )
+                            .await?;
+                            dialogue.exit().await?;
+                        }
+                    },
+                    None => {
+                        bot.send_message(msg.chat.id, "It looks like there's no such game")
+                            .await?;
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        LinkState::GetPreviousGameId { game_id } => match msg.text().map(ToOwned::to_owned) {
+            Some(previous_game) => {
+                let Some(previous_game) = runner.find_game(&previous_game) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match runner.link_previous_game(
+                    &UserId::from(msg.chat.id),
+                    &game_id,
+                    &previous_game,
+                ) {
+                    Ok(()) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "You've linked this game to its previous edition.",
+                        )
+                        .await?;
+                    }
+                    Err(error) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            format!("Unable to link these games.\n{error}"),
+                        )
+                        .await?;
+                    }
+                }
+                dialogue.exit().await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn archive(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    _state: ArchiveState,
+    runner: Runner,
+) -> HandlerResult {
+    match msg.text().map(ToOwned::to_owned) {
+        Some(game_id) => {
+            let Some(game_id) = runner.find_game(&game_id) else {
+                bot.send_message(msg.chat.id, "It looks like there's no such game")
+                    .await?;
+                dialogue.exit().await?;
+                return Ok(());
+            };
+            let user_id = UserId::from(msg.chat.id);
+
+            match runner.get_game(&game_id) {
+                Some(game) => match game.allows(&user_id, Permission::Owner) {
+                    true => match runner.archive_game(&game_id) {
+                        Ok(()) => {
+                            bot.send_message(msg.chat.id, "You've archived this game.")
+                                .await?;
+                        }
+                        Err(error) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Unable to archive this game.\n{error}"),
+                            )
+                            .await?;
+                        }
+                    },
+                    false => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "It looks like you're not allowed to do this in this game",
+                        )
+                        .await?;
+                    }
+                },
+                None => {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                }
+            }
+
+            dialogue.exit().await?;
+        }
+        None => {
+            bot.send_message(msg.chat.id, "Please use /help").await?;
+            dialogue.exit().await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn wishlist(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: WishlistState,
+    runner: Runner,
+) -> HandlerResult {
+    // "-" leaves a field of the wishlist empty
+    let answer = msg.text().map(|text| match text.trim() {
+        "-" => String::new(),
+        text => text.to_owned(),
+    });
+
+    match state {
+        WishlistState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                let user_id = UserId::from(msg.chat.id);
+
+                match runner.get_user(&user_id) {
+                    Some(user)
+                        if user.active_games.contains(&game_id)
+                            || user.pending_games.contains(&game_id) =>
+                    {
+                        if let Some(wishlist) = user.wishlist(&game_id) {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Here's your current wishlist:\n{wishlist}"),
+                            )
+                            .await?;
+                        }
+
+                        bot.send_message(
+                            msg.chat.id,
+                            "What would you like to get? Feel free to add links.\n\
+                                Send - to skip.",
+                        )
+                        .await?;
+
+                        dialogue
+                            .update(State::Wishlist {
+                                state: WishlistState::Wishes { game_id },
+                            })
+                            .await?;
+                    }
+                    _ => {
+                        bot.send_message(msg.chat.id, "It looks like you're not in this game")
+                            .await?;
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        WishlistState::Wishes { game_id } => match answer {
+            Some(wishes) => {
+                bot.send_message(
+                    msg.chat.id,
+                    "What are your sizes (clothes, shoes, rings)?\n\
+                        Send - to skip.",
+                )
+                .await?;
+
+                dialogue
+                    .update(State::Wishlist {
+                        state: WishlistState::Sizes { game_id, wishes },
+                    })
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        WishlistState::Sizes { game_id, wishes } => match answer {
+            Some(sizes) => {
+                bot.send_message(
+                    msg.chat.id,
+                    "Is there anything you'd rather not get?\n\
+                        Send - to skip.",
+                )
+                .await?;
+
+                dialogue
+                    .update(State::Wishlist {
+                        state: WishlistState::Dislikes {
+                            game_id,
+                            wishes,
+                            sizes,
+                        },
+                    })
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        WishlistState::Dislikes {
+            game_id,
+            wishes,
+            sizes,
+        } => match answer {
+            Some(dislikes) => {
+                let wishlist = Wishlist {
+                    wishes,
+                    sizes,
+                    dislikes,
+                };
+
+                match runner.set_wishlist(&UserId::from(msg.chat.id), &game_id, wishlist) {
+                    Ok(()) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "You've updated your wishlist. Your secret santa will see it.",
+                        )
+                        .await?;
+                    }
+                    Err(error) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            format!("Unable to update your wishlist.\n{error}"),
+                        )
+                        .await?;
+                    }
+                }
+                dialogue.exit().await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn relay(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: RelayState,
+    runner: Runner,
+) -> HandlerResult {
+    let user_id = UserId::from(msg.chat.id);
+
+    match state {
+        RelayState::GetGameId { direction } => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                let counterpart = match direction {
+                    RelayDirection::ToRecipient => runner.get_recipient(&game_id, &user_id),
+                    RelayDirection::ToSanta => runner.get_santa(&game_id, &user_id),
+                };
+
+                match (counterpart, direction) {
+                    (Some(_), RelayDirection::ToRecipient) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "Everything you send now will be forwarded to your recipient anonymously.\n\
+                                Text and photos are supported.\n\
+                                Use /cancel to stop.",
+                        )
+                        .await?;
+                    }
+                    (Some(_), RelayDirection::ToSanta) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "Everything you send now will be forwarded to your secret santa.\n\
+                                Text and photos are supported.\n\
+                                Use /cancel to stop.",
+                        )
+                        .await?;
+                    }
+                    (None, _) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "It looks like this game wasn't drawn or you're not in it",
+                        )
+                        .await?;
+                        dialogue.exit().await?;
+                        return Ok(());
+                    }
+                }
+
+                dialogue
+                    .update(State::Relay {
+                        state: RelayState::Chat { game_id, direction },
+                    })
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        RelayState::Chat { game_id, direction } => {
+            // commands aren't forwarded, they end the chat so that user can send them again
+            if msg.text().is_some_and(|text| text.starts_with('/')) {
+                bot.send_message(
+                    msg.chat.id,
+                    "You've stopped sending messages anonymously.\n\
+                    Please send this command again.",
+                )
+                .await?;
+                dialogue.exit().await?;
+                return Ok(());
+            }
+
+            let (counterpart, header, reply_command) = match direction {
+                RelayDirection::ToRecipient => (
+                    runner.get_recipient(&game_id, &user_id),
+                    "Your secret santa",
+                    "/tosanta",
+                ),
+                RelayDirection::ToSanta => (
+                    runner.get_santa(&game_id, &user_id),
+                    "The one you're preparing a present for",
+                    "/torecipient",
+                ),
+            };
+            let Some(counterpart) = counterpart else {
+                bot.send_message(msg.chat.id, "Unable to find who to send it to.")
+                    .await?;
+                dialogue.exit().await?;
+                return Ok(());
+            };
+
+            let Some(game) = runner.get_game(&game_id) else {
+                bot.send_message(msg.chat.id, "It looks like there's no such game")
+                    .await?;
+                dialogue.exit().await?;
+                return Ok(());
+            };
+            let game_name = game.name;
+            let header = format!("{header} from game {game_name} says:");
+            let footer = format!("You can reply with {reply_command}");
+
+            // messages are sent on behalf of the bot instead of being forwarded to keep the sender hidden
+            let sent = match (msg.text(), msg.photo()) {
+                (Some(text), _) => {
+                    bot.send_message(
+                        ChatId(counterpart.0),
+                        format!("{header}\n\n{text}\n\n{footer}"),
+                    )
+                    .await
+                }
+                (None, Some(photos)) => {
+                    // the last photo is the biggest one
+                    let photo = photos.last().unwrap();
+                    let caption = msg.caption().unwrap_or_default();
+                    bot.send_photo(
+                        ChatId(counterpart.0),
+                        InputFile::file_id(photo.file.id.clone()),
+                    )
+                    .caption(format!("{header}\n\n{caption}\n\n{footer}"))
+                    .await
+                }
+                (None, None) => {
+                    bot.send_message(msg.chat.id, "Only text and photos can be sent.")
+                        .await?;
+                    return Ok(());
+                }
+            };
+
+            if sent.is_err() {
+                bot.send_message(
+                    msg.chat.id,
+                    "Unable to deliver this message, please try again later.",
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn budget(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: BudgetState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        BudgetState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                let user_id = UserId::from(msg.chat.id);
+
+                match runner.get_game(&game_id) {
+                    Some(game) => match game.allows(&user_id, Permission::EditSettings) {
+                        true => {
+                            if let Some(budget) = game.budget {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    format!("Current budget is {budget}."),
+                                )
+                                .await?;
+                            }
+                            bot.send_message(msg.chat.id, BUDGET_QUESTION).await?;
+
+                            dialogue
+                                .update(State::Budget {
+                                    state: BudgetState::GetBudget { game_id },
+                                })
+                                .await?;
+                        }
+                        false => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "It looks like you're not allowed to do this in this game",
+                            )
+                            .await?;
+                            dialogue.exit().await?;
+                        }
+                    },
+                    None => {
+                        bot.send_message(msg.chat.id, "It looks like there's no such game")
+                            .await?;
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        BudgetState::GetBudget { game_id } => match msg.text().map(parse_budget) {
+            Some(Ok(budget)) => {
+                match runner.set_budget(&game_id, budget) {
+                    Ok(()) => {
+                        bot.send_message(msg.chat.id, "You've updated budget of this game.")
+                            .await?;
+                    }
+                    Err(error) => {
+                        bot.send_message(msg.chat.id, format!("Unable to update budget.\n{error}"))
+                            .await?;
+                    }
+                }
+                dialogue.exit().await?;
+            }
+            Some(Err(())) => {
+                bot.send_message(
+                    msg.chat.id,
+                    "Unable to understand this budget.\n Please retry or use /cancel",
+                )
+                .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn schedule(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: ScheduleState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        ScheduleState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                let user_id = UserId::from(msg.chat.id);
+
+                match runner.get_game(&game_id) {
+                    Some(game) => match game.allows(&user_id, Permission::Run) {
+                        true => {
+                            if let Some(draw_at) = game.draw_at {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    format!("This game will be drawn at {draw_at}."),
+                                )
+                                .await?;
+                            }
+                            bot.send_message(
+                                msg.chat.id,
+                                "When should this game be drawn?\n\
+                                    Please answer like 2023-12-20 18:00 UTC+3\n\
+                                    Send - to cancel the scheduled draw.",
+                            )
+                            .await?;
+
+                            dialogue
+                                .update(State::Schedule {
+                                    state: ScheduleState::GetDateTime { game_id },
+                                })
+                                .await?;
+                        }
+                        false => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "It looks like you're not allowed to do this in this game",
+                            )
+                            .await?;
+                            dialogue.exit().await?;
+                        }
+                    },
+                    None => {
+                        bot.send_message(msg.chat.id, "It looks like there's no such game")
+                            .await?;
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        ScheduleState::GetDateTime { game_id } => match msg.text().map(parse_date_time) {
+            Some(Ok(draw_at)) if draw_at.is_some_and(|draw_at| draw_at.timestamp <= now()) => {
+                bot.send_message(
+                    msg.chat.id,
+                    "This time has already passed.\n Please retry or use /cancel",
+                )
+                .await?;
+            }
+            Some(Ok(draw_at)) => {
+                match runner.set_draw_time(&game_id, draw_at) {
+                    Ok(()) => match draw_at {
+                        Some(draw_at) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("This game will be drawn automatically at {draw_at}."),
+                            )
+                            .await?;
+                        }
+                        None => {
+                            bot.send_message(msg.chat.id, "You've cancelled the scheduled draw.")
+                                .await?;
+                        }
+                    },
+                    Err(error) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            format!("Unable to schedule this game.\n{error}"),
+                        )
+                        .await?;
+                    }
+                }
+                dialogue.exit().await?;
+            }
+            Some(Err(())) => {
+                bot.send_message(
+                    msg.chat.id,
+                    "Unable to understand this time.\n Please retry or use /cancel",
+                )
+                .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn lock(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: LockState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        LockState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                let user_id = UserId::from(msg.chat.id);
+
+                match runner.get_game(&game_id) {
+                    Some(game) => match game.allows(&user_id, Permission::EditSettings) {
+                        true => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "When should registration to this game close?\n\
+                                    Send now to close it right away or a time like 2023-12-20 18:00 UTC+3",
+                            )
+                            .await?;
+
+                            dialogue
+                                .update(State::Lock {
+                                    state: LockState::GetDateTime { game_id },
+                                })
+                                .await?;
+                        }
+                        false => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "It looks like you're not allowed to do this in this game",
+                            )
+                            .await?;
+                            dialogue.exit().await?;
+                        }
+                    },
+                    None => {
+                        bot.send_message(msg.chat.id, "It looks like there's no such game")
+                            .await?;
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        LockState::GetDateTime { game_id } => {
+            let closes_at = msg
+                .text()
+                .map(|text| match text.trim().to_lowercase().as_str() {
+                    "now" => Ok(None),
+                    text => text.parse::<DateTime>().map(Some),
+                });
+
+            match closes_at {
+                Some(Ok(closes_at)) => {
+                    match runner.lock_game(&game_id, closes_at) {
+                        Ok(()) => match closes_at {
+                            Some(closes_at) => {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    format!("Registration to this game will close at {closes_at}."),
+                                )
+                                .await?;
+                            }
+                            None => {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    "Registration to this game is closed. You can /unlock it later.",
+                                )
+                                .await?;
+                            }
+                        },
+                        Err(error) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Unable to lock this game.\n{error}"),
+                            )
+                            .await?;
+                        }
+                    }
+                    dialogue.exit().await?;
+                }
+                Some(Err(())) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Unable to understand this time.\n Please retry or use /cancel",
+                    )
+                    .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn unlock(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    _state: UnlockState,
+    runner: Runner,
+) -> HandlerResult {
+    match msg.text().map(ToOwned::to_owned) {
+        Some(game_id) => {
+            let Some(game_id) = runner.find_game(&game_id) else {
+                bot.send_message(msg.chat.id, "It looks like there's no such game")
+                    .await?;
+                dialogue.exit().await?;
+                return Ok(());
+            };
+            let user_id = UserId::from(msg.chat.id);
+
+            match runner.get_game(&game_id) {
+                Some(game) => match game.allows(&user_id, Permission::EditSettings) {
+                    true => match runner.unlock_game(&game_id) {
+                        Ok(()) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "Registration to this game is open again.",
+                            )
+                            .await?;
+                        }
+                        Err(error) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Unable to unlock this game.\n{error}"),
+                            )
+                            .await?;
+                        }
+                    },
+                    false => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "It looks like you're not allowed to do this in this game",
+                        )
+                        .await?;
+                    }
+                },
+                None => {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                }
+            }
+
+            dialogue.exit().await?;
+        }
+        None => {
+            bot.send_message(msg.chat.id, "Please use /help").await?;
+            dialogue.exit().await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn policy(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: PolicyState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        PolicyState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                let Some(game) = authorized_game(
+                    &bot,
+                    msg.chat.id,
+                    &runner,
+                    &game_id,
+                    Permission::EditSettings,
+                )
+                .await?
+                else {
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                let join_policy = game.join_policy;
+                bot.send_message(
+                    msg.chat.id,
+                    format!("Current join policy: `{join_policy}`\n\n{JOIN_POLICY_QUESTION}"),
+                )
+                .parse_mode(ParseMode::MarkdownV2)
+                .await?;
+
+                dialogue
+                    .update(State::Policy {
+                        state: PolicyState::ChoosePolicy { game_id },
+                    })
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        PolicyState::ChoosePolicy { game_id } => {
+            let join_policy = match msg.text().map(|text| text.trim().to_lowercase()) {
+                Some(text) => match text.as_str() {
+                    "manual" => Some(JoinPolicy::Manual),
+                    "auto" => Some(JoinPolicy::AutoAccept),
+                    "invite" => Some(JoinPolicy::InviteOnly),
+                    "password" => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "Please enter the password.\n\
+                                You can /cancel",
+                        )
+                        .await?;
+                        dialogue
+                            .update(State::Policy {
+                                state: PolicyState::GetPassword { game_id },
+                            })
+                            .await?;
+                        return Ok(());
+                    }
+                    _ => {
+                        bot.send_message(msg.chat.id, JOIN_POLICY_QUESTION)
+                            .parse_mode(ParseMode::MarkdownV2)
+                            .await?;
+                        return Ok(());
+                    }
+                },
+                None => None,
+            };
+
+            match join_policy {
+                Some(join_policy) => {
+                    set_join_policy(&bot, msg.chat.id, &runner, &game_id, join_policy).await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                }
+            }
+            dialogue.exit().await?;
+        }
+        PolicyState::GetPassword { game_id } => match msg.text().map(|text| text.trim()) {
+            Some(password) if !password.is_empty() => {
+                let join_policy = JoinPolicy::Password(password.to_owned());
+                set_join_policy(&bot, msg.chat.id, &runner, &game_id, join_policy).await?;
+                dialogue.exit().await?;
+            }
+            _ => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+async fn set_join_policy(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    join_policy: JoinPolicy,
+) -> HandlerResult {
+    match runner.set_join_policy(game_id, join_policy.clone()) {
+        Ok(()) => {
+            bot.send_message(
+                chat_id,
+                format!("Join policy of this game is now {join_policy}."),
+            )
+            .await?;
+        }
+        Err(error) => {
+            bot.send_message(
+                chat_id,
+                format!("Unable to change join policy of this game.\n{error}"),
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn grant(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: GrantState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        GrantState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match offer_users_to_grant(&bot, msg.chat.id, &runner, game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Grant {
+                                state: GrantState::ChooseUser { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        GrantState::ChooseUser { game_id } => {
+            match msg.text().map(|text| text.trim().parse::<i64>()) {
+                Some(Ok(user_id)) => {
+                    let user_id = UserId(user_id);
+                    match ask_permissions(&bot, msg.chat.id, &runner, &game_id).await? {
+                        true => {
+                            dialogue
+                                .update(State::Grant {
+                                    state: GrantState::GetPermissions { game_id, user_id },
+                                })
+                                .await?;
+                        }
+                        false => {
+                            dialogue.exit().await?;
+                        }
+                    }
+                }
+                Some(Err(_)) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Please press one of the buttons above or use /cancel",
+                    )
+                    .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        GrantState::GetPermissions { game_id, user_id } => {
+            match msg.text().map(parse_permissions) {
+                Some(Ok(permissions)) => {
+                    if authorized_game(&bot, msg.chat.id, &runner, &game_id, Permission::Owner)
+                        .await?
+                        .is_some()
+                    {
+                        match runner.grant_permissions(&game_id, &user_id, permissions) {
+                            Ok(()) => {
+                                bot.send_message(msg.chat.id, "Done! You can /revoke it any time.")
+                                    .await?;
+                            }
+                            Err(error) => {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    format!("Unable to grant permissions.\n{error}"),
+                                )
+                                .await?;
+                            }
+                        }
+                    }
+                    dialogue.exit().await?;
+                }
+                Some(Err(())) => {
+                    bot.send_message(msg.chat.id, PERMISSIONS_QUESTION)
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn offer_users_to_grant(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, &game_id, Permission::Owner).await?
+    else {
+        return Ok(false);
+    };
+
+    offer_users(
+        bot,
+        chat_id,
+        runner,
+        &[game.active_users, game.pending_users].concat(),
+        |user_id| Action::GrantUser(game_id, user_id),
+        "Please choose who should help you.",
+    )
+    .await
+}
+
+async fn ask_permissions(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    if authorized_game(bot, chat_id, runner, game_id, Permission::Owner)
+        .await?
+        .is_none()
+    {
+        return Ok(false);
+    }
+
+    bot.send_message(chat_id, PERMISSIONS_QUESTION)
+        .parse_mode(ParseMode::MarkdownV2)
+        .await?;
+
+    Ok(true)
+}
+
+async fn revoke(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: RevokeState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        RevokeState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match offer_co_admins(&bot, msg.chat.id, &runner, game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Revoke {
+                                state: RevokeState::ChooseUser { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        RevokeState::ChooseUser { game_id } => {
+            match msg.text().map(|text| text.trim().parse::<i64>()) {
+                Some(Ok(user_id)) => {
+                    revoke_permissions(&bot, msg.chat.id, &runner, &game_id, &UserId(user_id))
+                        .await?;
+                    dialogue.exit().await?;
+                }
+                Some(Err(_)) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Please press one of the buttons above or use /cancel",
+                    )
+                    .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn offer_co_admins(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, &game_id, Permission::Owner).await?
+    else {
+        return Ok(false);
+    };
+
+    let co_admins: Vec<UserId> = game
+        .co_admins
+        .iter()
+        .map(|co_admin| co_admin.user_id)
+        .collect();
+    offer_users(
+        bot,
+        chat_id,
+        runner,
+        &co_admins,
+        |user_id| Action::RevokeUser(game_id, user_id),
+        "Please choose who shouldn't help you anymore.",
+    )
+    .await
+}
+
+async fn revoke_permissions(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    user_id: &UserId,
+) -> HandlerResult {
+    if authorized_game(bot, chat_id, runner, game_id, Permission::Owner)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    match runner.revoke_permissions(game_id, user_id) {
+        Ok(()) => {
+            bot.send_message(chat_id, "This user can't manage this game anymore.")
+                .await?;
+        }
+        Err(error) => {
+            bot.send_message(chat_id, format!("Unable to revoke permissions.\n{error}"))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn clone(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: CloneState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        CloneState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match ask_clone_name(&bot, msg.chat.id, &runner, &game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Clone {
+                                state: CloneState::GetName { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        CloneState::GetName { game_id } => match msg.text().map(ToOwned::to_owned) {
+            Some(game_name) => {
+                let Some(game) = runner.get_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                // "-" means the suggested name
+                let game_name = match game_name.trim() {
+                    "-" => next_edition_name(&game.name),
+                    _ => game_name,
+                };
+
+                bot.send_message(
+                    msg.chat.id,
+                    "Should I invite everyone who took part in the old game?\n\
+                    They'll only have to confirm it.\n\
+                    Please answer yes or no.",
+                )
+                .await?;
+                dialogue
+                    .update(State::Clone {
+                        state: CloneState::ChooseInvite { game_id, game_name },
+                    })
+                    .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        CloneState::ChooseInvite { game_id, game_name } => {
+            match msg.text().map(|text| text.trim().to_lowercase()) {
+                Some(answer) if answer == "yes" || answer == "no" => {
+                    if authorized_game(&bot, msg.chat.id, &runner, &game_id, Permission::Owner)
+                        .await?
+                        .is_some()
+                    {
+                        match runner.clone_game(&game_id, game_name, answer == "yes") {
+                            Ok(game_id) => {
+                                announce_game(&bot, msg.chat.id, &runner, &game_id).await?;
+                            }
+                            Err(error) => {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    format!("Unable to start this game again.\n{error}"),
+                                )
+                                .await?;
+                            }
+                        }
+                    }
+                    dialogue.exit().await?;
+                }
+                Some(_) => {
+                    bot.send_message(msg.chat.id, "Please answer yes or no or use /cancel")
+                        .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// returns false if user can't clone this game
+async fn ask_clone_name(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, game_id, Permission::Owner).await?
+    else {
+        return Ok(false);
+    };
+
+    let name = markdown::escape_code(&next_edition_name(&game.name));
+    bot.send_message(
+        chat_id,
+        format!(
+            "Please enter name of the new game\\.\n\
+            Send \\- to call it `{name}`\\.\n\
+            You can /cancel"
+        ),
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .await?;
+
+    Ok(true)
+}
+
+async fn repeat(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    state: RepeatState,
+    runner: Runner,
+) -> HandlerResult {
+    match state {
+        RepeatState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match ask_repeat_time(&bot, msg.chat.id, &runner, &game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Repeat {
+                                state: RepeatState::GetDateTime { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        RepeatState::GetDateTime { game_id } => match msg.text().map(parse_date_time) {
+            Some(Ok(first_draw)) => {
+                if authorized_game(
+                    &bot,
+                    msg.chat.id,
+                    &runner,
+                    &game_id,
+                    Permission::EditSettings,
+                )
+                .await?
+                .is_some()
+                {
+                    match runner.set_recurrence(&game_id, first_draw) {
+                        Ok(()) => {
+                            match runner.get_game(&game_id).and_then(|game| game.recurrence) {
+                                Some(recurrence) => {
+                                    bot.send_message(
+                                        msg.chat.id,
+                                        format!(
+                                            "This game will be drawn at {} and repeat every year, \
+                                        its next edition will be drawn at {}.\n\
+                                        It will be created right after the draw of this one.",
+                                            recurrence.draw_at(),
+                                            recurrence.next().draw_at()
+                                        ),
+                                    )
+                                    .await?;
+                                }
+                                None => {
+                                    bot.send_message(
+                                        msg.chat.id,
+                                        "This game won't repeat anymore and its draw isn't scheduled.\n\
+                                        You can /schedule it again.",
+                                    )
+                                    .await?;
+                                }
+                            }
+                        }
+                        Err(error) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Unable to make this game repeat.\n{error}"),
+                            )
+                            .await?;
+                        }
+                    }
+                }
+                dialogue.exit().await?;
+            }
+            Some(Err(())) => {
+                bot.send_message(
+                    msg.chat.id,
+                    "Unable to understand this date.\n Please retry or use /cancel",
                 )
                 .await?;
             }
-            _ => {
-                bot.send_message(msg.chat.id, "Cancelling the dialogue.")
-                    .await?;
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
                 dialogue.exit().await?;
             }
-        }
+        },
     }
+
     Ok(())
 }
 
-async fn invalid_state(bot: Bot, msg: Message) -> HandlerResult {
+/// returns false if user can't make this game repeat
+async fn ask_repeat_time(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) =
+        authorized_game(bot, chat_id, runner, game_id, Permission::EditSettings).await?
+    else {
+        return Ok(false);
+    };
+    if game.is_drawn() {
+        bot.send_message(
+            chat_id,
+            "It looks like this game was already drawn, you can /clone it",
+        )
+        .await?;
+        return Ok(false);
+    }
+
+    if let Some(recurrence) = game.recurrence {
+        bot.send_message(
+            chat_id,
+            format!("This game repeats every year at {}.", recurrence.draw_at()),
+        )
+        .await?;
+    }
     bot.send_message(
-        msg.chat.id,
-        "Unable to handle the message. Type /help to see the usage.",
+        chat_id,
+        "When should the yearly draw happen?\n\
+            Please answer like 2023-12-20 18:00 UTC+3, only the day and time matter.\n\
+            Send - to stop repeating the game.",
     )
     .await?;
-    Ok(())
+
+    Ok(true)
 }
 
-async fn register(
+async fn delete(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    _state: RegisterState,
+    state: DeleteState,
     runner: Runner,
 ) -> HandlerResult {
-    match msg.text().map(ToOwned::to_owned) {
-        Some(name) => {
-            runner.new_user(msg.chat.id.into(), name.clone());
+    match state {
+        DeleteState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match offer_delete_confirmation(&bot, msg.chat.id, &runner, game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Delete {
+                                state: DeleteState::Confirm { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        DeleteState::Confirm { game_id } => match msg.text().map(ToOwned::to_owned) {
+            Some(text) => {
+                let id = game_id.0;
+                match text == format!("Yes, I do want to delete game {id}") {
+                    true => {
+                        delete_game(&bot, msg.chat.id, &runner, &game_id).await?;
+                        dialogue.exit().await?;
+                    }
+                    false => {
+                        bot.send_message(
+                            msg.chat.id,
+                            "Text doesn't match confirnation statement.\n \
+                            Please retry or use /cancel",
+                        )
+                        .await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// asks admin to confirm the deletion, returns false if the game can't be deleted
+async fn offer_delete_confirmation(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, &game_id, Permission::Owner).await?
+    else {
+        return Ok(false);
+    };
+    if game.is_drawn() {
+        bot.send_message(
+            chat_id,
+            "It looks like this game was already drawn, you can /archive it instead",
+        )
+        .await?;
+        return Ok(false);
+    }
+
+    let id = game_id.0;
+    let name = markdown::escape_code(&game.name);
+    let keyboard = InlineKeyboardMarkup::new([[InlineKeyboardButton::callback(
+        "Yes, delete it",
+        Action::ConfirmDelete(game_id).to_string(),
+    )]]);
+    bot.send_message(
+        chat_id,
+        format! {"Please confirm that you're going to delete game `{name}`\n\
+        This action is irreversible\n\
+        All participants will be told that the game was cancelled\n\n\
+        To confirm please press the button or type `Yes, I do want to delete game {id}`\n\
+        You can /cancel"},
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .reply_markup(keyboard)
+    .await?;
+
+    Ok(true)
+}
+
+async fn delete_game(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+) -> HandlerResult {
+    if authorized_game(bot, chat_id, runner, game_id, Permission::Owner)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+
+    match runner.delete_game(game_id) {
+        Ok(()) => {
+            bot.send_message(chat_id, "You've deleted this game.")
+                .await?;
+        }
+        Err(error) if error.is::<GameIsAlreadyDrawnError>() => {
             bot.send_message(
-                msg.chat.id,
-                format! {"Thanks for completing the registration, {name}.\n\
-                    You can change your username using /username\n\
-                Use /help to get more info."},
+                chat_id,
+                "It looks like this game was already drawn, you can /archive it instead",
             )
             .await?;
-
-            dialogue.exit().await?;
         }
-        None => {
-            bot.send_message(msg.chat.id, "Please use /help").await?;
-            dialogue.exit().await?;
+        Err(error) => {
+            bot.send_message(chat_id, format!("Unable to delete this game.\n{error}"))
+                .await?;
         }
     }
 
     Ok(())
 }
 
-async fn username(
+async fn transfer(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    _state: UsernameState,
+    state: TransferState,
     runner: Runner,
 ) -> HandlerResult {
-    match msg.text().map(ToOwned::to_owned) {
-        Some(name) => {
-            runner.change_username(&UserId::from(msg.chat.id), name.clone());
+    match state {
+        TransferState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+
+                match offer_users_to_transfer(&bot, msg.chat.id, &runner, game_id).await? {
+                    true => {
+                        dialogue
+                            .update(State::Transfer {
+                                state: TransferState::ChooseUser { game_id },
+                            })
+                            .await?;
+                    }
+                    false => {
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        TransferState::ChooseUser { game_id } => {
+            match msg.text().map(|text| text.trim().parse::<i64>()) {
+                Some(Ok(user_id)) => {
+                    offer_transfer(&bot, msg.chat.id, &runner, &game_id, &UserId(user_id)).await?;
+                    dialogue.exit().await?;
+                }
+                Some(Err(_)) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "Please press one of the buttons above or use /cancel",
+                    )
+                    .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn offer_users_to_transfer(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: GameId,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let Some(game) = authorized_game(bot, chat_id, runner, &game_id, Permission::Owner).await?
+    else {
+        return Ok(false);
+    };
+
+    let users: Vec<UserId> = game
+        .active_users
+        .iter()
+        .filter(|user_id| **user_id != game.admin)
+        .copied()
+        .collect();
+    offer_users(
+        bot,
+        chat_id,
+        runner,
+        &users,
+        |user_id| Action::TransferUser(game_id, user_id),
+        "Please choose the new admin, they'll have to accept it.",
+    )
+    .await
+}
+
+async fn offer_transfer(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    user_id: &UserId,
+) -> HandlerResult {
+    if authorized_game(bot, chat_id, runner, game_id, Permission::Owner)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
 
+    match runner.offer_transfer(game_id, user_id) {
+        Ok(()) => {
             bot.send_message(
-                msg.chat.id,
-                format! {"You've changed your username to {name}."},
+                chat_id,
+                "Done! You'll stay the admin until they accept the game.",
             )
             .await?;
-
-            dialogue.exit().await?;
         }
-        None => {
-            bot.send_message(msg.chat.id, "Please use /help").await?;
-            dialogue.exit().await?;
+        Err(error) => {
+            bot.send_message(chat_id, format!("Unable to hand over the game.\n{error}"))
+                .await?;
         }
     }
 
     Ok(())
 }
 
-async fn create(
+async fn party(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    _state: CreateState,
+    state: PartyState,
     runner: Runner,
 ) -> HandlerResult {
-    match msg.text().map(ToOwned::to_owned) {
-        Some(game_name) => match runner.new_game(UserId::from(msg.chat.id), game_name.clone()) {
-            Ok(game_id) => {
-                let id = game_id.0;
+    match state {
+        PartyState::GetGameId => match msg.text().map(ToOwned::to_owned) {
+            Some(game_id) => {
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
+                let user_id = UserId::from(msg.chat.id);
+
+                match runner.get_game(&game_id) {
+                    Some(game) => match game.allows(&user_id, Permission::EditSettings) {
+                        true => {
+                            if let Some(party_at) = game.party_at {
+                                bot.send_message(
+                                    msg.chat.id,
+                                    format!("The party of this game is at {party_at}."),
+                                )
+                                .await?;
+                            }
+                            bot.send_message(
+                                msg.chat.id,
+                                "When is the gift exchange party?\n\
+                                    Please answer like 2023-12-24 19:00 UTC+3\n\
+                                    Send - if there's no party.",
+                            )
+                            .await?;
+
+                            dialogue
+                                .update(State::Party {
+                                    state: PartyState::GetDateTime { game_id },
+                                })
+                                .await?;
+                        }
+                        false => {
+                            bot.send_message(
+                                msg.chat.id,
+                                "It looks like you're not allowed to do this in this game",
+                            )
+                            .await?;
+                            dialogue.exit().await?;
+                        }
+                    },
+                    None => {
+                        bot.send_message(msg.chat.id, "It looks like there's no such game")
+                            .await?;
+                        dialogue.exit().await?;
+                    }
+                }
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
+            }
+        },
+        PartyState::GetDateTime { game_id } => match msg.text().map(parse_date_time) {
+            Some(Ok(party_at)) if party_at.is_some_and(|party_at| party_at.timestamp <= now()) => {
                 bot.send_message(
                     msg.chat.id,
-                    format! {"You've created game named {game_name} with game id `{id}`"},
+                    "This time has already passed.\n Please retry or use /cancel",
                 )
-                .parse_mode(ParseMode::MarkdownV2)
                 .await?;
-                bot.send_message(
-                msg.chat.id,
-                format! {"To join game {game_name} you have to use /join after registration and use `{id}`\\."},
-            )
-            .parse_mode(ParseMode::MarkdownV2)
-            .await?;
-
+            }
+            Some(Ok(party_at)) => {
+                match runner.set_party_time(&game_id, party_at) {
+                    Ok(()) => match party_at {
+                        Some(party_at) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!(
+                                    "The party of this game is at {party_at}.\n\
+                                    You can /remind participants about it."
+                                ),
+                            )
+                            .await?;
+                        }
+                        None => {
+                            bot.send_message(msg.chat.id, "You've cancelled the party.")
+                                .await?;
+                        }
+                    },
+                    Err(error) => {
+                        bot.send_message(
+                            msg.chat.id,
+                            format!("Unable to set the party time.\n{error}"),
+                        )
+                        .await?;
+                    }
+                }
                 dialogue.exit().await?;
             }
-            Err(_) => {
-                todo!()
+            Some(Err(())) => {
+                bot.send_message(
+                    msg.chat.id,
+                    "Unable to understand this time.\n Please retry or use /cancel",
+                )
+                .await?;
+            }
+            None => {
+                bot.send_message(msg.chat.id, "Please use /help").await?;
+                dialogue.exit().await?;
             }
         },
-        None => {
-            bot.send_message(msg.chat.id, "Please use /help").await?;
-            dialogue.exit().await?;
-        }
     }
 
     Ok(())
 }
 
-async fn run(
+async fn remind(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    state: RunState,
+    state: RemindState,
     runner: Runner,
 ) -> HandlerResult {
     match state {
-        RunState::GetId => match msg.text().map(ToOwned::to_owned) {
+        RemindState::GetGameId => match msg.text().map(ToOwned::to_owned) {
             Some(game_id) => {
-                let game_id = GameId::from(game_id);
+                let Some(game_id) = runner.find_game(&game_id) else {
+                    bot.send_message(msg.chat.id, "It looks like there's no such game")
+                        .await?;
+                    dialogue.exit().await?;
+                    return Ok(());
+                };
                 let user_id = UserId::from(msg.chat.id);
 
                 match runner.get_game(&game_id) {
-                    Some(game) => match game.admin == user_id {
+                    Some(game) => match game.allows(&user_id, Permission::EditSettings) {
                         true => {
-                            let id = game_id.0;
-                            let name = game.name;
-                            bot.send_message(
-                                    msg.chat.id,
-                                    format! {"Please confirm that you're going to run game `{name}`\n\
-                                        This action is irreversible\n\
-                                        Messages about who to give the gift to will be sent out instantly\n\n\
-                                        To confirm please type `Yes, I do want to run game {id}`\n\
-                                        You can /cancel"},
-                                ).parse_mode(ParseMode::MarkdownV2)
+                            if !game.reminders.is_empty() {
+                                let mut message = String::from("Current reminders:\n");
+                                for reminder in &game.reminders {
+                                    message.push_str(describe_reminder(reminder).as_str());
+                                }
+                                bot.send_message(msg.chat.id, message)
+                                    .parse_mode(ParseMode::MarkdownV2)
+                                    .await?;
+                            }
+                            bot.send_message(msg.chat.id, REMINDER_EVENT_QUESTION)
+                                .parse_mode(ParseMode::MarkdownV2)
                                 .await?;
+
                             dialogue
-                                .update(State::Run {
-                                    state: RunState::Confirm { game_id },
+                                .update(State::Remind {
+                                    state: RemindState::ChooseEvent { game_id },
                                 })
                                 .await?;
                         }
                         false => {
                             bot.send_message(
                                 msg.chat.id,
-                                "It looks like you're not admin of this game",
+                                "It looks like you're not allowed to do this in this game",
                             )
                             .await?;
                             dialogue.exit().await?;
@@ -606,291 +5255,348 @@ async fn run(
                 dialogue.exit().await?;
             }
         },
-        RunState::Confirm { game_id } => match msg.text().map(ToOwned::to_owned) {
-            Some(text) => {
-                let id = game_id.0;
-                match text == format!("Yes, I do want to run game {id}") {
-                    true => {
-                        bot.send_message(
-                            msg.chat.id,
-                            "You've successfully ran this game.\n\
-                                 Messages will be sent immediately\n\
-                                Thanks for using this bot!",
-                        )
-                        .await?;
+        RemindState::ChooseEvent { game_id } => {
+            match msg.text().map(|text| text.parse::<ReminderEvent>()) {
+                Some(Ok(event)) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "How many hours before should participants be reminded?\n\
+                            Please answer like 48 for two days before.\n\
+                            Send - to remove all reminders of this kind.",
+                    )
+                    .await?;
 
-                        let messages = runner.run_game(game_id).unwrap();
+                    dialogue
+                        .update(State::Remind {
+                            state: RemindState::GetHours { game_id, event },
+                        })
+                        .await?;
+                }
+                Some(Err(())) => {
+                    bot.send_message(msg.chat.id, REMINDER_EVENT_QUESTION)
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .await?;
+                }
+                None => {
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
+                }
+            }
+        }
+        RemindState::GetHours { game_id, event } => {
+            let hours_before = msg.text().map(|text| match text.trim() {
+                "-" => Ok(None),
+                text => text.parse::<u32>().map(Some),
+            });
 
-                        for (recipient, message) in messages {
-                            bot.send_message(ChatId(recipient.0), message).await?;
+            match hours_before {
+                Some(Ok(Some(hours_before))) if hours_before > 0 => {
+                    match runner.add_reminder(&game_id, event, hours_before) {
+                        Ok(()) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!(
+                                    "Participants will be reminded {hours_before} hours before the {event}."
+                                ),
+                            )
+                            .await?;
+                        }
+                        Err(error) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Unable to add this reminder.\n{error}"),
+                            )
+                            .await?;
                         }
-
-                        dialogue.exit().await?;
                     }
-                    false => {
-                        bot.send_message(msg.chat.id, "Text doesn't match confirnation statement.\n Please retry or use /cancel").await?;
+                    dialogue.exit().await?;
+                }
+                Some(Ok(None)) => {
+                    match runner.remove_reminders(&game_id, event) {
+                        Ok(()) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Participants won't be reminded about the {event}."),
+                            )
+                            .await?;
+                        }
+                        Err(error) => {
+                            bot.send_message(
+                                msg.chat.id,
+                                format!("Unable to remove these reminders.\n{error}"),
+                            )
+                            .await?;
+                        }
                     }
+                    dialogue.exit().await?;
                 }
-            }
-            None => {
-                bot.send_message(msg.chat.id, "Please use /help").await?;
-                dialogue.exit().await?;
-            }
-        },
-    }
-
-    Ok(())
-}
-
-async fn join(
-    bot: Bot,
-    dialogue: MyDialogue,
-    msg: Message,
-    _state: JoinState,
-    runner: Runner,
-) -> HandlerResult {
-    match msg.text().map(ToOwned::to_owned) {
-        Some(game_id) => {
-            let game_id = GameId::from(game_id);
-            let user_id = UserId::from(msg.chat.id);
-
-            match runner.get_game(&game_id) {
                 Some(_) => {
-                    runner.add_user_to_pending(&user_id, &game_id);
-
                     bot.send_message(
                         msg.chat.id,
-                        "You're now in the waiting list to this game.\n\
-                    Please wait until game administrator confirms you.\n\
-                    You can /leave to leave game and /list to list all your games.",
+                        "Please enter a positive number of hours or use /cancel",
                     )
                     .await?;
                 }
                 None => {
-                    bot.send_message(
-                        msg.chat.id,
-                        "It looks like there's no such game.\n\
-                    Please use /help",
-                    )
-                    .await?;
+                    bot.send_message(msg.chat.id, "Please use /help").await?;
+                    dialogue.exit().await?;
                 }
             }
-
-            dialogue.exit().await?;
-        }
-        None => {
-            bot.send_message(msg.chat.id, "Please use /help").await?;
-            dialogue.exit().await?;
         }
     }
 
     Ok(())
 }
 
-async fn leave(
-    bot: Bot,
-    dialogue: MyDialogue,
-    msg: Message,
-    _state: LeaveState,
-    runner: Runner,
-) -> HandlerResult {
-    match msg.text().map(ToOwned::to_owned) {
-        Some(game_id) => {
-            let user_id = UserId::from(msg.chat.id);
-            let game_id = GameId::from(game_id);
-
-            runner.remove_user_from_game(&user_id, &game_id).unwrap();
+// "-" means there's no date
+fn parse_date_time(text: &str) -> Result<Option<DateTime>, ()> {
+    match text.trim() {
+        "-" => Ok(None),
+        text => text.parse::<DateTime>().map(Some),
+    }
+}
 
-            bot.send_message(msg.chat.id, "You've successfully left this game.")
-                .await?;
-            dialogue.exit().await?;
-        }
-        None => {
-            bot.send_message(msg.chat.id, "Please use /help").await?;
-            dialogue.exit().await?;
+// "all" means every permission that can be granted
+fn parse_permissions(text: &str) -> Result<Vec<Permission>, ()> {
+    match text.trim().to_lowercase().as_str() {
+        "all" => Ok(vec![
+            Permission::Approve,
+            Permission::Remove,
+            Permission::EditSettings,
+            Permission::Run,
+        ]),
+        text => {
+            let mut permissions: Vec<Permission> = Vec::new();
+            for permission in text.split(',') {
+                let permission = permission.parse::<Permission>()?;
+                if !permissions.contains(&permission) {
+                    permissions.push(permission);
+                }
+            }
+            Ok(permissions)
         }
     }
-
-    Ok(())
 }
 
-async fn accept(
-    bot: Bot,
-    dialogue: MyDialogue,
-    msg: Message,
-    state: AcceptState,
-    runner: Runner,
-) -> HandlerResult {
-    match state {
-        AcceptState::GetGameId => match msg.text().map(ToOwned::to_owned) {
-            Some(game_id) => {
-                //TODO do proper checks
-                let game_id = GameId::from(game_id);
-
-                let mut message = String::from("Here are all pendling users:\n");
-
-                runner
-                    .get_game(&game_id)
-                    .unwrap()
-                    .pending_users
-                    .iter()
-                    .map(|id| runner.get_user(id).unwrap())
-                    .for_each(|user| message.push_str(user.to_string().as_str()));
-
-                bot.send_message(msg.chat.id, message)
-                    .parse_mode(ParseMode::MarkdownV2)
-                    .await?;
-
-                bot.send_message(msg.chat.id, "Please send id of the user to accept.")
-                    .await?;
-
-                dialogue
-                    .update(State::Accept {
-                        state: AcceptState::GetUserId { game_id },
-                    })
-                    .await?;
-            }
-            None => {
-                bot.send_message(msg.chat.id, "Please use /help").await?;
-                dialogue.exit().await?;
-            }
-        },
-        AcceptState::GetUserId { game_id } => match msg.text().map(ToOwned::to_owned) {
-            Some(user_id) => {
-                let user_id = UserId::from(user_id);
+// returns `None` unless text is id of an active or pending user of the game
+fn parse_game_user(runner: &Runner, game_id: &GameId, text: &str) -> Option<UserId> {
+    let user_id = UserId(text.trim().parse::<i64>().ok()?);
+    let game = runner.get_game(game_id)?;
+    match game.active_users.contains(&user_id) || game.pending_users.contains(&user_id) {
+        true => Some(user_id),
+        false => None,
+    }
+}
 
-                runner
-                    .promote_user_from_pending_to_active(&user_id, &game_id)
-                    .unwrap();
+// "-" means there's no budget
+fn parse_budget(text: &str) -> Result<Option<Budget>, ()> {
+    match text.trim() {
+        "-" => Ok(None),
+        text => text.parse::<Budget>().map(Some),
+    }
+}
 
-                bot.send_message(msg.chat.id, "You've accepted this user to the game.")
-                    .await?;
-                dialogue.exit().await?;
-            }
-            None => {
-                bot.send_message(msg.chat.id, "Please use /help").await?;
-                dialogue.exit().await?;
+/// returns the game if user has the permission, otherwise explains why it's not
+async fn authorized_game(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
+    permission: Permission,
+) -> Result<Option<Game>, Box<dyn Error + Send + Sync>> {
+    match runner.get_game(game_id) {
+        Some(game) => match game.allows(&UserId::from(chat_id), permission) {
+            true => Ok(Some(game)),
+            false => {
+                bot.send_message(
+                    chat_id,
+                    "It looks like you're not allowed to do this in this game",
+                )
+                .await?;
+                Ok(None)
             }
         },
+        None => {
+            bot.send_message(chat_id, "It looks like there's no such game")
+                .await?;
+            Ok(None)
+        }
     }
-
-    Ok(())
 }
 
-async fn remove(
-    bot: Bot,
-    dialogue: MyDialogue,
-    msg: Message,
-    state: RemoveState,
-    runner: Runner,
-) -> HandlerResult {
-    match state {
-        RemoveState::GetGameId => match msg.text().map(ToOwned::to_owned) {
-            Some(game_id) => {
-                //TODO do proper checks
-                let game_id = GameId::from(game_id);
-
-                let mut message = String::from("Here are all users:\n\n");
-
-                message.push_str("Pending users:\n\n");
-
-                let game = runner.get_game(&game_id).unwrap();
-
-                game.pending_users
-                    .iter()
-                    .map(|id| runner.get_user(id).unwrap())
-                    .for_each(|user| message.push_str(user.to_string().as_str()));
-
-                message.push_str("Active users:\n\n");
-
-                game.active_users
-                    .iter()
-                    .map(|id| runner.get_user(id).unwrap())
-                    .for_each(|user| message.push_str(user.to_string().as_str()));
+fn managed_games(runner: &Runner, user_id: &UserId, permission: Permission) -> Vec<Game> {
+    user_games(runner, user_id, |user| {
+        [user.admin_games, user.co_admin_games].concat()
+    })
+    .into_iter()
+    .filter(|game| game.allows(user_id, permission))
+    .collect()
+}
 
-                bot.send_message(msg.chat.id, message)
-                    .parse_mode(ParseMode::MarkdownV2)
-                    .await?;
+fn user_games(
+    runner: &Runner,
+    user_id: &UserId,
+    select: impl Fn(User) -> Vec<GameId>,
+) -> Vec<Game> {
+    runner
+        .get_user(user_id)
+        .map(select)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|game_id| runner.get_game(game_id))
+        .collect()
+}
 
-                bot.send_message(msg.chat.id, "Please send id of the user to remove.")
-                    .await?;
+/// sends a button for every game, returns false if there's nothing to choose from
+async fn offer_games(
+    bot: &Bot,
+    chat_id: ChatId,
+    games: Vec<Game>,
+    action: fn(GameId) -> Action,
+    prompt: &str,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    if games.is_empty() {
+        bot.send_message(
+            chat_id,
+            "It looks like there are no suitable games.\n\
+                You can /list all your games.",
+        )
+        .await?;
+        return Ok(false);
+    }
 
-                dialogue
-                    .update(State::Remove {
-                        state: RemoveState::GetUserId { game_id },
-                    })
-                    .await?;
-            }
-            None => {
-                bot.send_message(msg.chat.id, "Please use /help").await?;
-                dialogue.exit().await?;
-            }
-        },
-        RemoveState::GetUserId { game_id } => match msg.text().map(ToOwned::to_owned) {
-            Some(user_id) => {
-                let user_id = UserId::from(user_id);
+    let keyboard = InlineKeyboardMarkup::new(games.iter().map(|game| {
+        [InlineKeyboardButton::callback(
+            game.name.clone(),
+            action(game.id).to_string(),
+        )]
+    }));
+    bot.send_message(
+        chat_id,
+        format!("{prompt}\nYou can also enter its code or /cancel"),
+    )
+    .reply_markup(keyboard)
+    .await?;
 
-                runner.remove_user_from_game(&user_id, &game_id);
+    Ok(true)
+}
 
-                bot.send_message(msg.chat.id, "You've removed this user from the game.")
-                    .await?;
-                dialogue.exit().await?;
-            }
-            None => {
-                bot.send_message(msg.chat.id, "Please use /help").await?;
-                dialogue.exit().await?;
-            }
-        },
+/// sends a button for every user, returns false if there's nothing to choose from
+async fn offer_users(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    users: &[UserId],
+    action: impl Fn(UserId) -> Action,
+    prompt: &str,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    if users.is_empty() {
+        bot.send_message(chat_id, "It looks like there are no users to choose from.")
+            .await?;
+        return Ok(false);
     }
 
-    Ok(())
+    let keyboard = InlineKeyboardMarkup::new(users.iter().map(|user_id| {
+        [InlineKeyboardButton::callback(
+            name_of(runner, user_id),
+            action(*user_id).to_string(),
+        )]
+    }));
+    bot.send_message(
+        chat_id,
+        format!("{prompt}\nYou can also enter their id or /cancel"),
+    )
+    .reply_markup(keyboard)
+    .await?;
+
+    Ok(true)
 }
 
-async fn info(
-    bot: Bot,
-    dialogue: MyDialogue,
-    msg: Message,
-    _state: InfoState,
-    runner: Runner,
+/// tells admin how others can join the game they've just created
+async fn announce_game(
+    bot: &Bot,
+    chat_id: ChatId,
+    runner: &Runner,
+    game_id: &GameId,
 ) -> HandlerResult {
-    match msg.text().map(ToOwned::to_owned) {
-        Some(game_id) => {
-            let game_id = GameId::from(game_id);
-            let game = runner.get_game(&game_id).unwrap();
-
-            let mut message = String::from("Here are info about your game:\n\n");
-
-            let game_name = game.name;
-            message.push_str(format!("Name: `{game_name}`\n\n").as_str());
+    let game = runner.get_game(game_id).unwrap();
+    let game_name = markdown::escape(&game.name);
+    let code = game.code.unwrap();
+    bot.send_message(
+        chat_id,
+        format! {"You've created game named {game_name} with game code `{code}`"},
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .await?;
+    bot.send_message(
+        chat_id,
+        format! {"To join game {game_name} you have to use /join after registration and use `{code}`\\."},
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .await?;
+    let link = invite_link(bot, runner, game_id).await?;
+    bot.send_message(
+        chat_id,
+        format!("Or just share this link, it does both:\n{link}"),
+    )
+    .await?;
 
-            let game_id = game_id.0;
-            message.push_str(format!("Id: `{game_id}`\n\n").as_str());
+    Ok(())
+}
 
-            message.push_str("Active users:\n");
+async fn invite_link(
+    bot: &Bot,
+    runner: &Runner,
+    game_id: &GameId,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let token = runner.invite_token(game_id)?;
+    let me = bot.get_me().await?;
+    Ok(format!(
+        "https://t.me/{}?start=join_{}",
+        me.username(),
+        token.0
+    ))
+}
 
-            game.active_users
-                .iter()
-                .map(|id| runner.get_user(id).unwrap())
-                .for_each(|user| message.push_str(user.to_string().as_str()));
+/// falls back to the id if the user is gone
+fn name_of(runner: &Runner, user_id: &UserId) -> String {
+    runner
+        .get_user(user_id)
+        .map(|user| user.username)
+        .unwrap_or_else(|| user_id.to_string())
+}
 
-            message.push_str("\nPending users:\n");
+/// name and id of the user for markdown messages, only the id is known if the user is gone
+fn describe_user(runner: &Runner, user_id: &UserId) -> String {
+    match runner.get_user(user_id) {
+        Some(user) => user.to_string(),
+        None => format!("Id: `{}`\n\n", user_id.0),
+    }
+}
 
-            game.pending_users
-                .iter()
-                .map(|id| runner.get_user(id).unwrap())
-                .for_each(|user| message.push_str(user.to_string().as_str()));
+fn describe_reminder(reminder: &Reminder) -> String {
+    format!(
+        "`{}` hours before the `{}`\n",
+        reminder.hours_before, reminder.event
+    )
+}
 
-            bot.send_message(msg.chat.id, message)
-                .parse_mode(ParseMode::MarkdownV2)
-                .await?;
+fn describe_exclusion(runner: &Runner, exclusion: &Exclusion) -> String {
+    let name = |id: &UserId| markdown::escape_code(&name_of(runner, id));
 
-            dialogue.exit().await?;
-        }
-        None => {
-            bot.send_message(msg.chat.id, "Please use /help").await?;
-            dialogue.exit().await?;
-        }
+    match exclusion {
+        Exclusion::OneWay {
+            presenter,
+            recipient,
+        } => format!(
+            "`{}` won't prepare a present for `{}`\n",
+            name(presenter),
+            name(recipient)
+        ),
+        Exclusion::BothWays(a, b) => format!(
+            "`{}` and `{}` won't prepare presents for each other\n",
+            name(a),
+            name(b)
+        ),
     }
-
-    Ok(())
 }
@@ -1,7 +1,11 @@
 use serde::{Deserialize, Serialize};
 use sled::IVec;
-use std::fmt;
-use teloxide::types::ChatId;
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use teloxide::{types::ChatId, utils::markdown};
 
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub struct UserId(pub i64);
@@ -12,12 +16,6 @@ impl From<ChatId> for UserId {
     }
 }
 
-impl From<String> for UserId {
-    fn from(value: String) -> Self {
-        Self(value.parse::<i64>().unwrap())
-    }
-}
-
 impl fmt::Display for UserId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -33,9 +31,17 @@ impl UserId {
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub struct GameId(pub u64);
 
-impl From<String> for GameId {
-    fn from(value: String) -> Self {
-        Self(value.parse::<u64>().unwrap())
+impl FromStr for GameId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().parse::<u64>().map(GameId)
+    }
+}
+
+impl From<IVec> for GameId {
+    fn from(value: IVec) -> Self {
+        ron::from_str(std::str::from_utf8(value.as_ref()).unwrap()).unwrap()
     }
 }
 
@@ -51,6 +57,81 @@ impl GameId {
     }
 }
 
+/// short code people type instead of the full game id, it's stored as a key pointing to the game
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct GameCode(pub String);
+
+impl GameCode {
+    pub fn to_key(&self) -> String {
+        ron::to_string(self).unwrap()
+    }
+}
+
+impl FromStr for GameCode {
+    type Err = ();
+
+    /// codes are case insensitive
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().to_uppercase();
+        match code.len() == GAME_CODE_LENGTH
+            && code.bytes().all(|c| GAME_CODE_ALPHABET.contains(&c))
+        {
+            true => Ok(GameCode(code)),
+            false => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for GameCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+pub const GAME_CODE_LENGTH: usize = 6;
+/// there are no characters that are easy to confuse like 0/O or 1/I/l
+pub const GAME_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+
+/// secret part of an invite link, it's stored as a key pointing to the game
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct InviteToken(pub String);
+
+impl InviteToken {
+    pub fn to_key(&self) -> String {
+        ron::to_string(self).unwrap()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Wishlist {
+    /// free text, may contain links
+    pub wishes: String,
+    pub sizes: String,
+    /// things recipient doesn't want to get
+    pub dislikes: String,
+}
+
+impl fmt::Display for Wishlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.wishes.is_empty() {
+            writeln!(f, "Wishes: {}", self.wishes)?;
+        }
+        if !self.sizes.is_empty() {
+            writeln!(f, "Sizes: {}", self.sizes)?;
+        }
+        if !self.dislikes.is_empty() {
+            writeln!(f, "Please don't give: {}", self.dislikes)?;
+        }
+        Ok(())
+    }
+}
+
+impl Wishlist {
+    pub fn is_empty(&self) -> bool {
+        self.wishes.is_empty() && self.sizes.is_empty() && self.dislikes.is_empty()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct User {
     pub id: UserId,
@@ -58,11 +139,21 @@ pub struct User {
     pub admin_games: Vec<GameId>,
     pub active_games: Vec<GameId>,
     pub pending_games: Vec<GameId>,
+    #[serde(default)]
+    pub wishlists: Vec<(GameId, Wishlist)>,
+    /// games user helps to manage, see `Game::co_admins`
+    #[serde(default)]
+    pub co_admin_games: Vec<GameId>,
 }
 
 impl fmt::Display for User {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Name: `{}`\n Id: `{}`\n\n", self.username, self.id.0)
+        write!(
+            f,
+            "Name: `{}`\n Id: `{}`\n\n",
+            markdown::escape_code(&self.username),
+            self.id.0
+        )
     }
 }
 
@@ -76,6 +167,456 @@ impl User {
     pub fn to_ron(&self) -> String {
         ron::to_string(&self).unwrap()
     }
+    pub fn wishlist(&self, game_id: &GameId) -> Option<&Wishlist> {
+        self.wishlists
+            .iter()
+            .find(|(id, _)| id == game_id)
+            .map(|(_, wishlist)| wishlist)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Exclusion {
+    /// `presenter` can't prepare a present for `recipient`, the opposite is allowed
+    OneWay {
+        presenter: UserId,
+        recipient: UserId,
+    },
+    /// neither of two users can prepare a present for the other one
+    BothWays(UserId, UserId),
+}
+
+impl Exclusion {
+    pub fn forbids(&self, presenter: &UserId, recipient: &UserId) -> bool {
+        match self {
+            Exclusion::OneWay {
+                presenter: from,
+                recipient: to,
+            } => from == presenter && to == recipient,
+            Exclusion::BothWays(a, b) => {
+                (a == presenter && b == recipient) || (b == presenter && a == recipient)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DrawMode {
+    /// any assignment where nobody prepares a present for themselves
+    #[default]
+    Pairs,
+    /// everyone is a part of one big chain: A -> B -> C -> ... -> A
+    Chain,
+}
+
+impl fmt::Display for DrawMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawMode::Pairs => write!(f, "pairs"),
+            DrawMode::Chain => write!(f, "chain"),
+        }
+    }
+}
+
+impl FromStr for DrawMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "pairs" => Ok(DrawMode::Pairs),
+            "chain" => Ok(DrawMode::Chain),
+            _ => Err(()),
+        }
+    }
+}
+
+/// what co-admins are allowed to do in a game, its admin is allowed everything
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Permission {
+    /// accept and reject join requests
+    Approve,
+    /// remove participants
+    Remove,
+    /// change rules and settings of the game
+    EditSettings,
+    /// run the game or schedule it
+    Run,
+    /// only the admin has it, it can't be granted
+    Owner,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Permission::Approve => write!(f, "approve"),
+            Permission::Remove => write!(f, "remove"),
+            Permission::EditSettings => write!(f, "settings"),
+            Permission::Run => write!(f, "run"),
+            Permission::Owner => write!(f, "owner"),
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "approve" => Ok(Permission::Approve),
+            "remove" => Ok(Permission::Remove),
+            "settings" => Ok(Permission::EditSettings),
+            "run" => Ok(Permission::Run),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CoAdmin {
+    pub user_id: UserId,
+    pub permissions: Vec<Permission>,
+}
+
+/// admin has rejected user's request to join, user can't ask again until admin allows it
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Rejection {
+    pub user_id: UserId,
+    pub reason: Option<String>,
+}
+
+/// decides what happens when somebody wants to join a game
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub enum JoinPolicy {
+    /// everyone waits until admin accepts them
+    #[default]
+    Manual,
+    /// everyone becomes a participant right away
+    AutoAccept,
+    /// those who know the password become participants right away
+    Password(String),
+    /// only those who followed an invite link can join, they become participants right away
+    InviteOnly,
+}
+
+// password isn't shown since everyone in the game can see the policy
+impl fmt::Display for JoinPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinPolicy::Manual => write!(f, "manual"),
+            JoinPolicy::AutoAccept => write!(f, "auto"),
+            JoinPolicy::Password(_) => write!(f, "password"),
+            JoinPolicy::InviteOnly => write!(f, "invite"),
+        }
+    }
+}
+
+/// how user asks to join a game, it's checked against the join policy of the game
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum JoinRequest {
+    Code,
+    Invite,
+    Password(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JoinOutcome {
+    Pending,
+    Active,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GameStatus {
+    /// users can join the game
+    #[default]
+    Open,
+    /// users can't join the game, but it wasn't drawn yet
+    Closed,
+    /// presents were distributed, the game is kept for history
+    Drawn,
+    /// drawn game that its admin doesn't want to see anymore
+    Archived,
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameStatus::Open => write!(f, "open"),
+            GameStatus::Closed => write!(f, "closed"),
+            GameStatus::Drawn => write!(f, "drawn"),
+            GameStatus::Archived => write!(f, "archived"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Budget {
+    pub min: u32,
+    pub max: u32,
+    pub currency: String,
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.min {
+            0 => write!(f, "up to {} {}", self.max, self.currency),
+            _ => write!(f, "{}-{} {}", self.min, self.max, self.currency),
+        }
+    }
+}
+
+/// parses budgets like `10-50 EUR` or `50 EUR`
+impl FromStr for Budget {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (amount, currency) = s.trim().rsplit_once(' ').ok_or(())?;
+        let currency = currency.trim().to_uppercase();
+
+        let (min, max) = match amount.split_once('-') {
+            Some((min, max)) => (min.trim().parse(), max.trim().parse()),
+            None => (Ok(0), amount.trim().parse()),
+        };
+        let (Ok(min), Ok(max)) = (min, max) else {
+            return Err(());
+        };
+
+        if currency.is_empty() || min > max {
+            return Err(());
+        }
+
+        Ok(Budget { min, max, currency })
+    }
+}
+
+/// moment in time together with the time zone it was entered in
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DateTime {
+    /// unix timestamp in seconds
+    pub timestamp: u64,
+    /// offset from UTC in minutes
+    pub utc_offset: i32,
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let local = self.timestamp as i64 + self.utc_offset as i64 * 60;
+        let (year, month, day) = civil_from_days(local.div_euclid(86400));
+        let seconds = local.rem_euclid(86400);
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+            seconds / 3600,
+            seconds % 3600 / 60
+        )?;
+        if self.utc_offset != 0 {
+            let sign = if self.utc_offset < 0 { '-' } else { '+' };
+            let offset = self.utc_offset.abs();
+            write!(f, "{sign}{:02}:{:02}", offset / 60, offset % 60)?;
+        }
+        Ok(())
+    }
+}
+
+impl DateTime {
+    /// the same local date and time a number of years later,
+    /// February 29 becomes February 28 if that year isn't a leap one
+    pub fn years_later(&self, years: i64) -> DateTime {
+        let local = self.timestamp as i64 + self.utc_offset as i64 * 60;
+        let (year, month, day) = civil_from_days(local.div_euclid(86400));
+        let mut days = days_from_civil(year + years, month, day);
+        if civil_from_days(days) != (year + years, month, day) {
+            days = days_from_civil(year + years, month, day - 1);
+        }
+        let timestamp = days * 86400 + local.rem_euclid(86400) - self.utc_offset as i64 * 60;
+        DateTime {
+            timestamp: timestamp as u64,
+            utc_offset: self.utc_offset,
+        }
+    }
+}
+
+/// yearly draw date, every edition is counted from the first one so dates don't drift
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Recurrence {
+    pub first_draw: DateTime,
+    /// how many years after the first draw this edition is drawn
+    pub edition: u32,
+}
+
+impl Recurrence {
+    /// the first edition that's drawn after `now`
+    pub fn new(first_draw: DateTime, now: u64) -> Self {
+        let mut recurrence = Recurrence {
+            first_draw,
+            edition: 0,
+        };
+        while recurrence.draw_at().timestamp <= now {
+            recurrence = recurrence.next();
+        }
+        recurrence
+    }
+    pub fn draw_at(&self) -> DateTime {
+        self.first_draw.years_later(self.edition as i64)
+    }
+    pub fn next(&self) -> Self {
+        Recurrence {
+            edition: self.edition + 1,
+            ..*self
+        }
+    }
+}
+
+/// parses dates like `2023-12-20 18:00`, `2023-12-20 18:00 UTC+3` or `2023-12-20 18:00 -05:30`
+impl FromStr for DateTime {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let (Some(date), Some(time)) = (parts.next(), parts.next()) else {
+            return Err(());
+        };
+        let utc_offset = match parts.next() {
+            Some(zone) => parse_utc_offset(zone)?,
+            None => 0,
+        };
+        if parts.next().is_some() {
+            return Err(());
+        }
+
+        let mut date = date.split('-').map(|part| part.parse::<i64>());
+        let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day)), None) =
+            (date.next(), date.next(), date.next(), date.next())
+        else {
+            return Err(());
+        };
+        let (hour, minute) = time.split_once(':').ok_or(())?;
+        let (Ok(hour), Ok(minute)) = (hour.parse::<i64>(), minute.parse::<i64>()) else {
+            return Err(());
+        };
+
+        // keeps the arithmetic below far from overflowing
+        if !(1970..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day)
+        {
+            return Err(());
+        }
+
+        let days = days_from_civil(year, month, day);
+        // catches dates like 2023-02-30
+        if civil_from_days(days) != (year, month, day)
+            || !(0..24).contains(&hour)
+            || !(0..60).contains(&minute)
+        {
+            return Err(());
+        }
+
+        let timestamp = days * 86400 + hour * 3600 + minute * 60 - utc_offset as i64 * 60;
+        match u64::try_from(timestamp) {
+            Ok(timestamp) => Ok(DateTime {
+                timestamp,
+                utc_offset,
+            }),
+            Err(_) => Err(()),
+        }
+    }
+}
+
+// parses `UTC`, `UTC+3`, `GMT-05:30`, `+03:00` into minutes
+fn parse_utc_offset(zone: &str) -> Result<i32, ()> {
+    let offset = zone
+        .strip_prefix("UTC")
+        .or_else(|| zone.strip_prefix("GMT"))
+        .unwrap_or(zone);
+    if offset.is_empty() {
+        return Ok(0);
+    }
+
+    let (sign, offset) = match (offset.strip_prefix('+'), offset.strip_prefix('-')) {
+        (Some(offset), _) => (1, offset),
+        (_, Some(offset)) => (-1, offset),
+        _ => return Err(()),
+    };
+    if !offset.chars().all(|c| c.is_ascii_digit() || c == ':') {
+        return Err(());
+    }
+    let (hours, minutes) = match offset.split_once(':') {
+        Some((hours, minutes)) => (hours.parse::<i32>(), minutes.parse::<i32>()),
+        None => (offset.parse::<i32>(), Ok(0)),
+    };
+    let (Ok(hours), Ok(minutes)) = (hours, minutes) else {
+        return Err(());
+    };
+    if !(0..=14).contains(&hours) || !(0..60).contains(&minutes) {
+        return Err(());
+    }
+
+    Ok(sign * (hours * 60 + minutes))
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReminderEvent {
+    RegistrationCloses,
+    Draw,
+    Party,
+}
+
+impl fmt::Display for ReminderEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReminderEvent::RegistrationCloses => write!(f, "registration"),
+            ReminderEvent::Draw => write!(f, "draw"),
+            ReminderEvent::Party => write!(f, "party"),
+        }
+    }
+}
+
+impl FromStr for ReminderEvent {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "registration" => Ok(ReminderEvent::RegistrationCloses),
+            "draw" => Ok(ReminderEvent::Draw),
+            "party" => Ok(ReminderEvent::Party),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Reminder {
+    pub event: ReminderEvent,
+    pub hours_before: u32,
+    /// time of the event this reminder was sent for, it's sent again if the event is moved
+    pub sent_for: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -85,11 +626,65 @@ pub struct Game {
     pub admin: UserId,
     pub active_users: Vec<UserId>,
     pub pending_users: Vec<UserId>,
+    #[serde(default)]
+    pub exclusions: Vec<Exclusion>,
+    #[serde(default)]
+    pub draw_mode: DrawMode,
+    /// game that was played by the same group before, it's pairs are avoided in the draw
+    #[serde(default)]
+    pub previous_game: Option<GameId>,
+    #[serde(default)]
+    pub status: GameStatus,
+    #[serde(default)]
+    pub budget: Option<Budget>,
+    /// the game is drawn automatically at this time
+    #[serde(default)]
+    pub draw_at: Option<DateTime>,
+    /// nobody can join the game after this time
+    #[serde(default)]
+    pub registration_closes_at: Option<DateTime>,
+    /// time of the gift exchange party
+    #[serde(default)]
+    pub party_at: Option<DateTime>,
+    #[serde(default)]
+    pub reminders: Vec<Reminder>,
+    #[serde(default)]
+    pub invite_token: Option<InviteToken>,
+    /// games created before codes were introduced get it when the bot starts
+    #[serde(default)]
+    pub code: Option<GameCode>,
+    #[serde(default)]
+    pub join_policy: JoinPolicy,
+    #[serde(default)]
+    pub rejections: Vec<Rejection>,
+    #[serde(default)]
+    pub co_admins: Vec<CoAdmin>,
+    /// participant admin has offered the game to, they become admin once they accept
+    #[serde(default)]
+    pub transfer_to: Option<UserId>,
+    /// pending users that were invited by admin, they only have to confirm it
+    #[serde(default)]
+    pub invited_users: Vec<UserId>,
+    /// yearly games get their next edition right after the draw
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
 }
 
 impl fmt::Display for Game {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Name: `{}`\n Id: `{}`\n\n", self.name, self.id.0)
+        writeln!(f, "Name: `{}`", markdown::escape_code(&self.name))?;
+        if let Some(code) = &self.code {
+            writeln!(f, " Code: `{}`", code)?;
+        }
+        writeln!(f, " Id: `{}`", self.id.0)?;
+        if let Some(budget) = &self.budget {
+            writeln!(
+                f,
+                " Budget: `{}`",
+                markdown::escape_code(&budget.to_string())
+            )?;
+        }
+        writeln!(f)
     }
 }
 
@@ -103,4 +698,385 @@ impl Game {
     pub fn to_ron(&self) -> String {
         ron::to_string(&self).unwrap()
     }
+    pub fn is_drawn(&self) -> bool {
+        matches!(self.status, GameStatus::Drawn | GameStatus::Archived)
+    }
+    /// every admin check goes through it
+    pub fn allows(&self, user_id: &UserId, permission: Permission) -> bool {
+        match self.admin == *user_id {
+            true => true,
+            false => {
+                permission != Permission::Owner
+                    && self.co_admins.iter().any(|co_admin| {
+                        co_admin.user_id == *user_id && co_admin.permissions.contains(&permission)
+                    })
+            }
+        }
+    }
+    /// admin and co-admins that have the permission
+    pub fn managers(&self, permission: Permission) -> Vec<UserId> {
+        let mut managers = vec![self.admin];
+        managers.extend(
+            self.co_admins
+                .iter()
+                .filter(|co_admin| co_admin.permissions.contains(&permission))
+                .map(|co_admin| co_admin.user_id),
+        );
+        managers
+    }
+    pub fn event_time(&self, event: ReminderEvent) -> Option<DateTime> {
+        match event {
+            ReminderEvent::RegistrationCloses => self.registration_closes_at,
+            ReminderEvent::Draw => self.draw_at,
+            ReminderEvent::Party => self.party_at,
+        }
+    }
+    /// returns true if nobody can join the game at `now`
+    pub fn is_locked(&self, now: u64) -> bool {
+        self.status != GameStatus::Open
+            || self
+                .registration_closes_at
+                .is_some_and(|closes_at| closes_at.timestamp <= now)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AssignmentsId(pub GameId);
+
+impl AssignmentsId {
+    pub fn to_key(self) -> String {
+        ron::to_string(&self).unwrap()
+    }
+}
+
+/// result of the draw, pairs of previous editions are looked up here
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Assignments {
+    pub game_id: GameId,
+    pub game_name: String,
+    pub previous_game: Option<GameId>,
+    /// pairs of (presenter, recipient)
+    pub pairs: Vec<(UserId, UserId)>,
+}
+
+impl From<IVec> for Assignments {
+    fn from(value: IVec) -> Self {
+        ron::from_str(std::str::from_utf8(value.as_ref()).unwrap()).unwrap()
+    }
+}
+
+impl Assignments {
+    pub fn to_ron(&self) -> String {
+        ron::to_string(&self).unwrap()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OutboxId(pub u64);
+
+impl OutboxId {
+    pub fn to_key(self) -> String {
+        ron::to_string(&self).unwrap()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub enum DeliveryStatus {
+    Pending,
+    Sent,
+    Failed { reason: String },
+}
+
+/// name of the next edition of a game, the last year in the name is increased if there's any
+pub fn next_edition_name(name: &str) -> String {
+    let mut words: Vec<String> = name.split(' ').map(ToOwned::to_owned).collect();
+    if let Some(word) = words
+        .iter_mut()
+        .rev()
+        .find(|word| word.len() == 4 && word.chars().all(|c| c.is_ascii_digit()))
+    {
+        *word = (word.parse::<u32>().unwrap() + 1).to_string();
+    }
+    words.join(" ")
+}
+
+/// what happens when an inline keyboard button is pressed, it's sent as callback data
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Run(GameId),
+    ConfirmRun(GameId),
+    Leave(GameId),
+    Accept(GameId),
+    AcceptUser(GameId, UserId),
+    Reject(GameId),
+    RejectUser(GameId, UserId),
+    AllowRejoin(GameId, UserId),
+    Grant(GameId),
+    GrantUser(GameId, UserId),
+    Revoke(GameId),
+    RevokeUser(GameId, UserId),
+    Transfer(GameId),
+    TransferUser(GameId, UserId),
+    AcceptTransfer(GameId),
+    DeclineTransfer(GameId),
+    Delete(GameId),
+    ConfirmDelete(GameId),
+    Enrol(GameId),
+    Clone(GameId),
+    Repeat(GameId),
+    ConfirmInvitation(GameId),
+    DeclineInvitation(GameId),
+    Remove(GameId),
+    RemoveUser(GameId, UserId),
+    Info(GameId),
+}
+
+// telegram allows only 64 bytes of callback data, so it's kept short
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Run(game_id) => write!(f, "run:{game_id}"),
+            Action::ConfirmRun(game_id) => write!(f, "confirm_run:{game_id}"),
+            Action::Leave(game_id) => write!(f, "leave:{game_id}"),
+            Action::Accept(game_id) => write!(f, "accept:{game_id}"),
+            Action::AcceptUser(game_id, user_id) => write!(f, "accept:{game_id}:{user_id}"),
+            Action::Reject(game_id) => write!(f, "reject:{game_id}"),
+            Action::RejectUser(game_id, user_id) => write!(f, "reject:{game_id}:{user_id}"),
+            Action::AllowRejoin(game_id, user_id) => write!(f, "rejoin:{game_id}:{user_id}"),
+            Action::Grant(game_id) => write!(f, "grant:{game_id}"),
+            Action::GrantUser(game_id, user_id) => write!(f, "grant:{game_id}:{user_id}"),
+            Action::Revoke(game_id) => write!(f, "revoke:{game_id}"),
+            Action::RevokeUser(game_id, user_id) => write!(f, "revoke:{game_id}:{user_id}"),
+            Action::Transfer(game_id) => write!(f, "transfer:{game_id}"),
+            Action::TransferUser(game_id, user_id) => write!(f, "transfer:{game_id}:{user_id}"),
+            Action::AcceptTransfer(game_id) => write!(f, "take:{game_id}"),
+            Action::DeclineTransfer(game_id) => write!(f, "decline:{game_id}"),
+            Action::Delete(game_id) => write!(f, "delete:{game_id}"),
+            Action::ConfirmDelete(game_id) => write!(f, "confirm_delete:{game_id}"),
+            Action::Enrol(game_id) => write!(f, "enrol:{game_id}"),
+            Action::Clone(game_id) => write!(f, "clone:{game_id}"),
+            Action::Repeat(game_id) => write!(f, "repeat:{game_id}"),
+            Action::ConfirmInvitation(game_id) => write!(f, "in:{game_id}"),
+            Action::DeclineInvitation(game_id) => write!(f, "out:{game_id}"),
+            Action::Remove(game_id) => write!(f, "remove:{game_id}"),
+            Action::RemoveUser(game_id, user_id) => write!(f, "remove:{game_id}:{user_id}"),
+            Action::Info(game_id) => write!(f, "info:{game_id}"),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let game_id = || parts[1].parse::<GameId>().map_err(|_| ());
+        let user_id = || parts[2].parse::<i64>().map(UserId).map_err(|_| ());
+
+        match (parts[0], parts.len()) {
+            ("run", 2) => Ok(Action::Run(game_id()?)),
+            ("confirm_run", 2) => Ok(Action::ConfirmRun(game_id()?)),
+            ("leave", 2) => Ok(Action::Leave(game_id()?)),
+            ("accept", 2) => Ok(Action::Accept(game_id()?)),
+            ("accept", 3) => Ok(Action::AcceptUser(game_id()?, user_id()?)),
+            ("reject", 2) => Ok(Action::Reject(game_id()?)),
+            ("reject", 3) => Ok(Action::RejectUser(game_id()?, user_id()?)),
+            ("rejoin", 3) => Ok(Action::AllowRejoin(game_id()?, user_id()?)),
+            ("grant", 2) => Ok(Action::Grant(game_id()?)),
+            ("grant", 3) => Ok(Action::GrantUser(game_id()?, user_id()?)),
+            ("revoke", 2) => Ok(Action::Revoke(game_id()?)),
+            ("revoke", 3) => Ok(Action::RevokeUser(game_id()?, user_id()?)),
+            ("transfer", 2) => Ok(Action::Transfer(game_id()?)),
+            ("transfer", 3) => Ok(Action::TransferUser(game_id()?, user_id()?)),
+            ("take", 2) => Ok(Action::AcceptTransfer(game_id()?)),
+            ("decline", 2) => Ok(Action::DeclineTransfer(game_id()?)),
+            ("delete", 2) => Ok(Action::Delete(game_id()?)),
+            ("confirm_delete", 2) => Ok(Action::ConfirmDelete(game_id()?)),
+            ("enrol", 2) => Ok(Action::Enrol(game_id()?)),
+            ("clone", 2) => Ok(Action::Clone(game_id()?)),
+            ("repeat", 2) => Ok(Action::Repeat(game_id()?)),
+            ("in", 2) => Ok(Action::ConfirmInvitation(game_id()?)),
+            ("out", 2) => Ok(Action::DeclineInvitation(game_id()?)),
+            ("remove", 2) => Ok(Action::Remove(game_id()?)),
+            ("remove", 3) => Ok(Action::RemoveUser(game_id()?, user_id()?)),
+            ("info", 2) => Ok(Action::Info(game_id()?)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// message that is stored before sending so it's not lost if telegram is unavailable
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OutboxMessage {
+    pub id: OutboxId,
+    /// game the message is about, its admin gets a report once all of them are delivered or failed
+    pub game_id: Option<GameId>,
+    pub recipient: UserId,
+    pub text: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// unix timestamp in seconds
+    pub next_attempt: u64,
+    /// inline keyboard buttons sent along with the message
+    #[serde(default)]
+    pub buttons: Vec<(String, Action)>,
+}
+
+impl From<IVec> for OutboxMessage {
+    fn from(value: IVec) -> Self {
+        ron::from_str(std::str::from_utf8(value.as_ref()).unwrap()).unwrap()
+    }
+}
+
+impl OutboxMessage {
+    pub fn to_ron(&self) -> String {
+        ron::to_string(&self).unwrap()
+    }
+}
+
+/// current unix timestamp in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Option<DateTime> {
+        text.parse::<DateTime>().ok()
+    }
+
+    #[test]
+    fn parses_utc_date_time() {
+        let date_time = parse("2023-12-20 18:00").unwrap();
+        assert_eq!(date_time.timestamp, 1703095200);
+        assert_eq!(date_time.utc_offset, 0);
+        assert_eq!(date_time.to_string(), "2023-12-20 18:00 UTC");
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for text in [
+            "1970-01-01 00:00 UTC",
+            "2023-12-20 18:00 UTC+03:00",
+            "2024-02-29 23:59 UTC-05:30",
+            "9999-12-31 23:59 UTC+14:00",
+        ] {
+            assert_eq!(parse(text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn days_round_trip() {
+        for days in [-719468, -1, 0, 1, 19711, 2932896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn applies_offsets() {
+        let utc = parse("2023-12-20 18:00").unwrap().timestamp;
+        for (zone, minutes) in [
+            ("UTC", 0),
+            ("GMT", 0),
+            ("UTC+3", 180),
+            ("GMT-05:30", -330),
+            ("+03:00", 180),
+            ("-1", -60),
+        ] {
+            let date_time = parse(&format!("2023-12-20 18:00 {zone}")).unwrap();
+            assert_eq!(date_time.utc_offset, minutes);
+            assert_eq!(date_time.timestamp as i64, utc as i64 - minutes as i64 * 60);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_offsets() {
+        for zone in [
+            "€", "UTC€", "+€", "3", "UTC+15", "+03:60", "++3", "+-3", "UTC+",
+        ] {
+            assert!(
+                parse(&format!("2023-12-20 18:00 {zone}")).is_none(),
+                "{zone}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        for text in [
+            "",
+            "2023-12-20",
+            "2023-12-20 18:00 UTC extra",
+            "2023-02-29 12:00",
+            "2023-04-31 12:00",
+            "2023-13-01 12:00",
+            "2023-00-10 12:00",
+            "2023-12-00 12:00",
+            "2023-12-20 24:00",
+            "2023-12-20 18:60",
+            "1969-12-31 23:59",
+            "10000-01-01 00:00",
+            "1000000000000-01-01 00:00",
+            "2023-12-1000000000000000000 00:00",
+        ] {
+            assert!(parse(text).is_none(), "{text}");
+        }
+    }
+
+    #[test]
+    fn moves_to_the_same_date_of_later_years() {
+        let date_time = parse("2023-12-20 18:00 UTC+3").unwrap();
+        assert_eq!(
+            date_time.years_later(1).to_string(),
+            "2024-12-20 18:00 UTC+03:00"
+        );
+        assert_eq!(
+            date_time.years_later(10).to_string(),
+            "2033-12-20 18:00 UTC+03:00"
+        );
+    }
+
+    #[test]
+    fn february_29_becomes_february_28_in_common_years() {
+        let date_time = parse("2024-02-29 12:00").unwrap();
+        assert_eq!(date_time.years_later(1).to_string(), "2025-02-28 12:00 UTC");
+        assert_eq!(date_time.years_later(4).to_string(), "2028-02-29 12:00 UTC");
+    }
+
+    #[test]
+    fn recurrence_does_not_drift() {
+        let first_draw = parse("2024-02-29 12:00").unwrap();
+        let mut recurrence = Recurrence::new(first_draw, 0);
+        assert_eq!(recurrence.draw_at(), first_draw);
+        for _ in 0..4 {
+            recurrence = recurrence.next();
+        }
+        assert_eq!(recurrence.draw_at().to_string(), "2028-02-29 12:00 UTC");
+    }
+
+    #[test]
+    fn recurrence_starts_with_the_first_edition_ahead() {
+        let first_draw = parse("2020-12-24 18:00").unwrap();
+        let now = parse("2026-12-10 12:00").unwrap().timestamp;
+        let recurrence = Recurrence::new(first_draw, now);
+        assert_eq!(recurrence.draw_at().to_string(), "2026-12-24 18:00 UTC");
+        assert_eq!(
+            recurrence.next().draw_at().to_string(),
+            "2027-12-24 18:00 UTC"
+        );
+    }
+
+    #[test]
+    fn accepts_february_29_in_leap_years_only() {
+        assert!(parse("2024-02-29 12:00").is_some());
+        assert!(parse("2000-02-29 12:00").is_some());
+        assert!(parse("1900-02-29 12:00").is_none());
+        assert!(parse("2100-02-29 12:00").is_none());
+    }
 }